@@ -0,0 +1,132 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use walkdir::WalkDir;
+
+use crate::{
+    config::CONFIG,
+    store,
+    ui::ProgressBar,
+    utils::{self, SaveStat},
+};
+
+pub const ARCHIVE_EXTENSION: &str = ".tar.zst";
+
+pub fn is_archive(path: &Path) -> bool {
+    path.is_file() && path.to_string_lossy().ends_with(ARCHIVE_EXTENSION)
+}
+
+/// Packs `src` directory into `dst` archive, returns stats with both sizes
+pub fn pack_dir_with_progress(src: &Path, dst: &Path, progress_bar_title: Option<&str>) -> io::Result<SaveStat> {
+    let mut stat = SaveStat::scan(src);
+    let mut bar = ProgressBar::new(stat.count, progress_bar_title, 60);
+
+    // Write into a temporary file, so an interrupted save never looks like a complete one
+    let part_path = utils::sibling_path(dst, ".part");
+    let mut write = || -> io::Result<()> {
+        let encoder = zstd::Encoder::new(BufWriter::new(File::create(&part_path)?), 0)?;
+        let mut builder = tar::Builder::new(encoder);
+        let mut progress = 0;
+        for entry in WalkDir::new(src).min_depth(1) {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy() == CONFIG.cache_file_name {
+                continue;
+            }
+            let relative_path = entry.path().strip_prefix(src).unwrap();
+            if entry.file_type().is_dir() {
+                builder.append_dir(relative_path, entry.path())?;
+            } else {
+                builder.append_path_with_name(entry.path(), relative_path)?;
+                progress += 1;
+                bar.update(progress);
+            }
+        }
+        let file = builder
+            .into_inner()?
+            .finish()?
+            .into_inner()
+            .map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        return fs::rename(&part_path, dst);
+    };
+    if let Err(err) = write() {
+        fs::remove_file(&part_path).ok();
+        return Err(err);
+    }
+
+    stat.packed_size = Some(fs::metadata(dst)?.len());
+    return Ok(stat);
+}
+
+/// Unpacks `src` archive into a new `dst` directory
+pub fn unpack_with_progress(
+    src: &Path,
+    dst: &Path,
+    files_count: usize,
+    progress_bar_title: Option<&str>,
+) -> io::Result<()> {
+    let mut bar = ProgressBar::new(files_count, progress_bar_title, 60);
+    let decoder = zstd::Decoder::new(BufReader::new(File::open(src)?))?;
+    let mut archive = tar::Archive::new(decoder);
+    fs::create_dir(dst)?;
+    let mut progress = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let is_file = entry.header().entry_type().is_file();
+        entry.unpack_in(dst)?;
+        if is_file {
+            progress += 1;
+            bar.update(progress);
+        }
+    }
+    return Ok(());
+}
+
+/// Relative `/`-separated paths and sizes of the archived files, decompresses the whole archive
+pub fn files(src: &Path) -> io::Result<Vec<(String, u64)>> {
+    let decoder = zstd::Decoder::new(BufReader::new(File::open(src)?))?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type().is_file() {
+            files.push((store::relative_path_string(&entry.path()?), entry.header().size()?));
+        }
+    }
+    return Ok(files);
+}
+
+/// Relative `/`-separated paths, sizes and hashes of the archived files, decompresses the whole archive
+pub fn hashed_files(src: &Path) -> io::Result<Vec<(String, u64, String)>> {
+    let decoder = zstd::Decoder::new(BufReader::new(File::open(src)?))?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut files = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type().is_file() {
+            let path = store::relative_path_string(&entry.path()?);
+            let size = entry.header().size()?;
+            files.push((path, size, store::hash_reader(&mut entry)?));
+        }
+    }
+    return Ok(files);
+}
+
+/// Stats of the archive contents, decompresses the whole archive
+pub fn scan(src: &Path) -> io::Result<SaveStat> {
+    let decoder = zstd::Decoder::new(BufReader::new(File::open(src)?))?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut stat = SaveStat::default();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type().is_file() {
+            stat.size += entry.header().size()?;
+            stat.count += 1;
+        }
+    }
+    stat.packed_size = Some(fs::metadata(src)?.len());
+    return Ok(stat);
+}
@@ -1,79 +1,176 @@
-use console::style;
+use bytesize::ByteSize;
+use chrono::{DateTime, Local};
+use console::{Color, style};
+#[cfg(windows)]
 use lnks::Shortcut;
+#[cfg(windows)]
 use trim_margin::MarginTrimmable;
 
 use crate::{
-    config::{CONFIG, DEBUG},
-    ui,
-    utils::{self, SaveInfo},
+    archive,
+    config::{CONFIG, DEBUG, Storage},
+    game::{self, PlayerInfo, RunInfo},
+    processes, prune,
+    state::State,
+    steam::{self, CloudSync},
+    store, ui,
+    utils::{self, IndexedFile, SaveInfo, SaveMeta},
 };
+use serde_json::json;
+#[cfg(windows)]
+use std::env;
 use std::{
-    collections::{HashMap, HashSet},
-    env, fs,
-    path::PathBuf,
-    sync::LazyLock,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
 
+/// Why a command has failed, also used as the process exit code
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    NotFound = 2,
+    Validation = 3,
+    Io = 4,
+}
+
+impl Failure {
+    /// Status name for JSON output
+    pub fn status(self) -> &'static str {
+        match self {
+            Failure::NotFound => "not_found",
+            Failure::Validation => "validation",
+            Failure::Io => "io",
+        }
+    }
+}
+
 // Interactive functions-helpers
 
-fn interactive_check_saves_mb(saves_mb: Option<&Vec<SaveInfo>>) -> Option<&Vec<SaveInfo>> {
-    saves_mb.or_else(|| {
+fn interactive_check_saves_mb(saves_mb: Option<&Vec<SaveInfo>>) -> Result<&Vec<SaveInfo>, Failure> {
+    saves_mb.ok_or_else(|| {
         ui::error("Saves are unavailable");
-        return None;
+        return Failure::Io;
     })
 }
 
-fn interactive_parse_index(str_index: &str) -> Option<usize> {
-    str_index.parse().ok().or_else(|| {
+/// Empty answer cancels the command
+fn interactive_ask(prompt: &str) -> Result<String, Failure> {
+    ui::ask(prompt).ok_or(Failure::Validation)
+}
+
+fn interactive_confirm(prompt: &str) -> Result<bool, Failure> {
+    ui::confirm(prompt).ok_or(Failure::Validation)
+}
+
+/// Fails while the game is running, it keeps writing `save00` and overwrites it on exit.
+/// If processes cannot be listed, asks to make sure the game is closed
+fn interactive_check_game_closed() -> Result<(), Failure> {
+    if !CONFIG.check_game_running {
+        return Ok(());
+    }
+    match processes::is_noita_running() {
+        Ok(false) => return Ok(()),
+        Ok(true) => {
+            ui::error("Noita is running, quit the game first");
+            return Err(Failure::Validation);
+        }
+        Err(err) => {
+            ui::lnlnwrite_highlighted(
+                Color::Yellow,
+                &format!("Cannot tell whether Noita is running: {err}\nMake sure the game is closed"),
+            );
+            if !interactive_confirm("Is the game closed?")? {
+                ui::lnlnwrite("Cancelled");
+                return Err(Failure::Validation);
+            }
+            return Ok(());
+        }
+    }
+}
+
+/// Waits up to `steam_sync_wait` seconds for Steam Cloud to finish, then asks whether to go on anyway
+fn interactive_wait_for_cloud_sync() -> Result<bool, Failure> {
+    let Some(mut state) = steam::cloud_sync_state() else {
+        return Ok(true);
+    };
+    let started = Instant::now();
+    let wait = Duration::from_secs(CONFIG.steam_sync_wait as u64);
+    while state != CloudSync::Idle && started.elapsed() < wait {
+        ui::lnlnwrite(&format!("Waiting for Steam Cloud sync ({state})...")).update_later();
+        thread::sleep(Duration::from_secs(1));
+        state = steam::cloud_sync_state().unwrap_or(CloudSync::Idle);
+    }
+    if state == CloudSync::Idle {
+        return Ok(true);
+    }
+    ui::lnlnwrite_highlighted(
+        Color::Yellow,
+        &format!("Steam Cloud sync is not finished ({state})\nIt may overwrite the save files"),
+    );
+    let confirmed = interactive_confirm("Continue anyway?")?;
+    if !confirmed {
+        ui::lnlnwrite("Cancelled");
+    }
+    return Ok(confirmed);
+}
+
+fn interactive_parse_index(str_index: &str) -> Result<usize, Failure> {
+    str_index.parse().map_err(|_| {
         ui::error(&format!("Invalid index: {str_index}"));
-        return None;
+        return Failure::Validation;
     })
 }
 
-fn interactive_get_index_or_last(saves: &Vec<SaveInfo>, arg: Option<&str>) -> Option<usize> {
+fn interactive_get_index_or_last(saves: &[SaveInfo], arg: Option<&str>) -> Result<usize, Failure> {
     match arg {
-        None => Some(saves.len()),
+        None => Ok(saves.len()),
         Some(s) => interactive_parse_index(s),
     }
 }
 
-fn interactive_validate_save_index(saves: &Vec<SaveInfo>, index: usize) -> Option<()> {
+fn interactive_validate_save_index(saves: &[SaveInfo], index: usize) -> Result<(), Failure> {
     if index == 0 {
         ui::error("Index must be greater than 0");
-        return None;
+        return Err(Failure::Validation);
     }
-    if let Some(_) = saves.get(index - 1) {
-        return Some(());
+    if saves.get(index - 1).is_some() {
+        return Ok(());
     } else {
         ui::error(&format!("No save found by index: {}", index));
-        return None;
+        return Err(Failure::NotFound);
     }
 }
 
-fn interactive_get_save_by_index(saves: &Vec<SaveInfo>, index: usize) -> Option<&SaveInfo> {
+fn interactive_get_save_by_index(saves: &[SaveInfo], index: usize) -> Result<&SaveInfo, Failure> {
     interactive_validate_save_index(saves, index)?;
     if let Some(save) = saves.get(index - 1) {
-        return Some(save);
+        return Ok(save);
     } else {
         ui::error(&format!("No save found by index: {}", index));
-        return None;
+        return Err(Failure::NotFound);
     }
 }
 
-fn interactive_parse_slice(saves: &Vec<SaveInfo>, str_slice: &str) -> Option<(usize, usize)> {
-    if let Some(index) = str_slice.parse::<usize>().ok() {
+fn interactive_parse_slice(saves: &[SaveInfo], str_slice: &str) -> Result<(usize, usize), Failure> {
+    if let Ok(index) = str_slice.parse::<usize>() {
         interactive_validate_save_index(saves, index)?;
-        return Some((index, index));
+        return Ok((index, index));
     }
     if let Some((start, end)) = str_slice.split_once("..") {
-        let start = start.parse::<usize>().or_else(|_| {
+        let start = start.parse::<usize>().or({
             if start.is_empty() {
                 Result::Ok(1)
             } else {
                 Result::Err(())
             }
         });
-        let end = end.parse::<usize>().or_else(|_| {
+        let end = end.parse::<usize>().or({
             if end.is_empty() {
                 Result::Ok(saves.len())
             } else {
@@ -83,39 +180,39 @@ fn interactive_parse_slice(saves: &Vec<SaveInfo>, str_slice: &str) -> Option<(us
         if let (Ok(start), Ok(end)) = (start, end) {
             interactive_validate_save_index(saves, start)?;
             interactive_validate_save_index(saves, end)?;
-            return Some((start, end));
+            return Ok((start, end));
         }
     }
     ui::error(&format!("Invalid interval: {str_slice}"));
-    return None;
+    return Err(Failure::Validation);
 }
 
-fn interactive_get_slice(saves: &Vec<SaveInfo>, arg: Option<&str>) -> Option<(usize, usize)> {
+fn interactive_get_slice(saves: &[SaveInfo], arg: Option<&str>) -> Result<(usize, usize), Failure> {
     interactive_parse_slice(
         saves,
-        arg.map(|s| Some(s.to_string()))
-            .unwrap_or_else(|| ui::ask("Save index or interval ([from]..[to])"))?
+        arg.map(|s| Ok(s.to_string()))
+            .unwrap_or_else(|| interactive_ask("Save index or interval ([from]..[to])"))?
             .as_str(),
     )
 }
 
-fn interactive_get_saves_by_slice(saves: &Vec<SaveInfo>, slice: (usize, usize)) -> Option<&[SaveInfo]> {
+fn interactive_get_saves_by_slice(saves: &[SaveInfo], slice: (usize, usize)) -> Result<&[SaveInfo], Failure> {
     if let Some(saves) = saves.get(slice.0 - 1..=slice.1 - 1) {
-        return Some(saves);
+        return Ok(saves);
     } else {
         ui::error(&format!("No saves found by interval: {}..{}", slice.0, slice.1));
-        return None;
+        return Err(Failure::NotFound);
     }
 }
 
-fn interactive_validate_save_name(saves: &Vec<SaveInfo>, name: &str) -> Option<()> {
+fn interactive_validate_save_name(saves: &[SaveInfo], name: &str) -> Result<(), Failure> {
     if name.is_empty() {
         ui::error("Save name cannot be empty");
-        return None;
+        return Err(Failure::Validation);
     }
     if name.len() > 69 {
         ui::error("Save name is too long");
-        return None;
+        return Err(Failure::Validation);
     }
     let forbidden_chars = name
         .chars()
@@ -128,96 +225,1398 @@ fn interactive_validate_save_name(saves: &Vec<SaveInfo>, name: &str) -> Option<(
             forbidden_chars.iter().collect::<String>(),
             style("]").dim()
         ));
-        return None;
+        return Err(Failure::Validation);
     }
     if saves.iter().any(|save| save.name == name) {
         ui::error(&format!("Save with this name already exists: {}", name));
-        return None;
+        return Err(Failure::Validation);
     }
-    return Some(());
+    return Ok(());
+}
+
+fn interactive_get_save_name(arg: Option<&str>) -> Result<String, Failure> {
+    arg.map(|s| Ok(s.to_string()))
+        .unwrap_or_else(|| interactive_ask("Save name"))
 }
 
-fn interactive_get_save_name(arg: Option<&str>) -> Option<String> {
-    arg.map(|s| Some(s.to_string())).unwrap_or_else(|| ui::ask("Save name"))
+/// `<index> <name>` argument, name is asked later if missing
+fn interactive_split_index_and_name(arg: Option<&str>) -> Result<(String, Option<&str>), Failure> {
+    match arg {
+        Some(arg) => match arg.split_once(' ') {
+            Some((str_index, name)) => Ok((str_index.to_string(), Some(name.trim()))),
+            None => Ok((arg.to_string(), None)),
+        },
+        None => Ok((interactive_ask("Save index")?, None)),
+    }
 }
 
 // Commands
 
-fn cmd_test(_saves: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Option<()> {
-    return Some(());
+fn cmd_test(_saves: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Result<(), Failure> {
+    return Ok(());
 }
 
-fn cmd_save(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Option<()> {
+/// Stores the current progress as a new save, game state is added to `meta`
+fn interactive_store_current(name: &str, storage: Storage, meta: SaveMeta) -> Result<PathBuf, Failure> {
+    let (save_path, result) = match storage {
+        Storage::Dir => {
+            let save_path = CONFIG.saves_dir_path.join(name);
+            // Copy into a hidden directory first, so an interrupted save never looks like a complete one
+            let part_path = CONFIG.saves_dir_path.join(format!(".{name}.part"));
+            let copy = || -> io::Result<()> {
+                if part_path.exists() {
+                    fs::remove_dir_all(&part_path)?;
+                }
+                utils::copy_dir_with_progress(&CONFIG.current_save_path, &part_path, false, true, Some("Saving"))?;
+                return fs::rename(&part_path, &save_path);
+            };
+            let result = copy().inspect_err(|_| {
+                fs::remove_dir_all(&part_path).ok();
+            });
+            (save_path, result)
+        }
+        Storage::Archive => {
+            let save_path = CONFIG
+                .saves_dir_path
+                .join(name.to_string() + archive::ARCHIVE_EXTENSION);
+            let result = archive::pack_dir_with_progress(&CONFIG.current_save_path, &save_path, Some("Saving"))
+                .and_then(|stat| stat.write_cache(&save_path));
+            (save_path, result)
+        }
+        Storage::Dedup => {
+            let save_path = CONFIG.saves_dir_path.join(name.to_string() + store::MANIFEST_EXTENSION);
+            let result = store::store_dir_with_progress(&CONFIG.current_save_path, &save_path, Some("Saving"))
+                .and_then(|stat| stat.write_cache(&save_path));
+            (save_path, result)
+        }
+    };
+    if let Err(err) = result {
+        ui::error(&format!("Failed to save save: {}", err));
+        return Err(Failure::Io);
+    }
+    let meta = SaveMeta {
+        player: PlayerInfo::read(&CONFIG.current_save_path),
+        run: RunInfo::read(&CONFIG.current_save_path),
+        ..meta
+    };
+    if let Err(err) = meta.write(&save_path) {
+        ui::error(&format!("Failed to write save metadata: {}", err));
+    }
+    return Ok(save_path);
+}
+
+/// How an existing save is stored
+fn save_storage(save: &SaveInfo) -> Storage {
+    if archive::is_archive(&save.path) {
+        Storage::Archive
+    } else if store::is_manifest(&save.path) {
+        Storage::Dedup
+    } else {
+        Storage::Dir
+    }
+}
+
+/// Records that the current progress now comes from the save
+fn remember_loaded(name: &str) {
+    let state = State {
+        loaded: Some(name.to_string()),
+        loaded_at: Some(SystemTime::now()),
+    };
+    if let Err(err) = state.write() {
+        ui::error(&format!("Failed to remember the loaded save: {}", err));
+    }
+}
+
+fn cmd_save(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    interactive_check_game_closed()?;
     let saves = interactive_check_saves_mb(saves_mb)?;
     let save_name = interactive_get_save_name(arg)?;
     interactive_validate_save_name(saves, &save_name)?;
 
-    if let Err(err) = utils::copy_dir_with_progress(
-        &CONFIG.current_save_path,
-        &CONFIG.saves_dir_path.join(save_name),
-        false,
-        true,
-        Some("Saving"),
-    ) {
-        ui::error(&format!("Failed to save save: {}", err));
-        return None;
+    let save_path = interactive_store_current(&save_name, CONFIG.storage, SaveMeta::default())?;
+    remember_loaded(&save_name);
+    if let Some(save) = SaveInfo::new(save_path) {
+        ui::json_field(
+            "save",
+            save.to_json(Some(saves.len() + 1), SaveInfo::current().as_ref()),
+        );
+    }
+    return Ok(());
+}
+
+/// `<prefix> <date> <time> seed <seed> depth <y>` unique among `saves`, parts unknown for the current progress are left out
+fn snapshot_name(prefix: &str, saves: &[SaveInfo]) -> String {
+    let mut name = format!("{prefix} {}", Local::now().format("%Y-%m-%d %H-%M-%S"));
+    if let Some(seed) = RunInfo::read(&CONFIG.current_save_path).and_then(|run| run.seed) {
+        name.push_str(&format!(" seed {seed}"));
+    }
+    if let Some(player) = PlayerInfo::read(&CONFIG.current_save_path) {
+        name.push_str(&format!(" depth {:.0}", player.y));
+    }
+    return utils::unique_name(&name, |name| saves.iter().any(|s| s.name == name));
+}
+
+fn cmd_quicksave(saves_mb: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Result<(), Failure> {
+    interactive_check_game_closed()?;
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let save_name = snapshot_name("Quick", saves);
+    interactive_validate_save_name(saves, &save_name)?;
+
+    let meta = SaveMeta {
+        quick: true,
+        ..SaveMeta::default()
+    };
+    let save_path = interactive_store_current(&save_name, CONFIG.storage, meta)?;
+    remember_loaded(&save_name);
+    match utils::rotate_quicksaves() {
+        Ok(removed) => ui::json_field("removed", json!(removed)),
+        Err(err) => ui::error(&format!("Failed to remove old quick-saves: {}", err)),
+    }
+    if let Err(err) = store::collect_garbage() {
+        ui::error(&format!("Failed to clean up object store: {}", err));
+    }
+    ui::lnlnwrite(&format!("Quick-saved as {}", style(&save_name).bold()));
+    if let Some(save) = SaveInfo::new(save_path) {
+        ui::json_field("save", save.to_json(None, SaveInfo::current().as_ref()));
+    }
+    return Ok(());
+}
+
+/// `save00` must stay unchanged this long after the game exits to be snapshotted
+const WATCH_SETTLE: Duration = Duration::from_secs(10);
+
+/// Whether the game is running, watching is impossible without the process list
+fn interactive_watch_is_running() -> Result<bool, Failure> {
+    processes::is_noita_running().map_err(|err| {
+        ui::error(&format!(
+            "Cannot tell whether Noita is running: {}\nStopped watching",
+            err
+        ));
+        return Failure::Io;
+    })
+}
+
+/// Waits for the game to exit and saves its final progress, `watch <count>` stops after `count` snapshots
+fn cmd_watch(_saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    let limit = match arg {
+        None => None,
+        Some(arg) => Some(arg.parse::<usize>().ok().filter(|&count| count > 0).ok_or_else(|| {
+            ui::error(&format!("Invalid snapshots count: {arg}"));
+            return Failure::Validation;
+        })?),
+    };
+    let mut last_fingerprint = utils::fingerprint(&CONFIG.current_save_path).ok();
+    let mut was_running = interactive_watch_is_running()?;
+    // Set when the game exits, cleared once its progress is saved
+    let mut exited_at: Option<Instant> = None;
+    let mut marker = current_save_marker();
+    let mut snapshots = Vec::new();
+    let mut removed = Vec::new();
+    ui::lnlnwrite(&format!(
+        "Watching Noita, the progress is saved every time the game exits {}",
+        style("(Ctrl+C to stop)").dim()
+    ));
+    while limit.is_none_or(|limit| snapshots.len() < limit) {
+        let running = interactive_watch_is_running()?;
+        if running {
+            ui::lnlnwrite("Noita is running...").update_later();
+            exited_at = None;
+        } else if was_running {
+            exited_at = Some(Instant::now());
+        } else if exited_at.is_none() {
+            ui::lnlnwrite("Waiting for Noita to start...").update_later();
+        }
+        was_running = running;
+
+        let new_marker = current_save_marker();
+        if new_marker != marker {
+            // The game is still writing, wait until it settles
+            marker = new_marker;
+            if exited_at.is_some() {
+                exited_at = Some(Instant::now());
+            }
+        }
+        if let Some(changed_at) = exited_at {
+            ui::lnlnwrite("Noita has exited, waiting for the save to be written...").update_later();
+            if changed_at.elapsed() >= WATCH_SETTLE {
+                exited_at = None;
+                let fingerprint = utils::fingerprint(&CONFIG.current_save_path).ok();
+                // A failed snapshot is reported, the next exit is still watched
+                if fingerprint.is_some()
+                    && fingerprint != last_fingerprint
+                    && let Ok(save_name) = watch_snapshot(&mut removed)
+                {
+                    last_fingerprint = fingerprint;
+                    snapshots.push(save_name);
+                    ui::json_field("snapshots", json!(snapshots));
+                    ui::json_field("removed", json!(removed));
+                    continue;
+                }
+            }
+        }
+        thread::sleep(Duration::from_secs(2));
+    }
+    return Ok(());
+}
+
+/// Saves the current progress under a timestamped name the same way as `save`,
+/// then moves the oldest exit snapshots beyond `watch_saves_limit` to the trash
+fn watch_snapshot(removed: &mut Vec<String>) -> Result<String, Failure> {
+    let saves = SaveInfo::all().unwrap_or_default();
+    let save_name = snapshot_name("Exit", &saves);
+    interactive_validate_save_name(&saves, &save_name)?;
+    let meta = SaveMeta {
+        exit: true,
+        ..SaveMeta::default()
+    };
+    interactive_store_current(&save_name, CONFIG.storage, meta)?;
+    remember_loaded(&save_name);
+    match utils::rotate_watch_saves() {
+        Ok(names) => removed.extend(names),
+        Err(err) => ui::error(&format!("Failed to move old exit snapshots to the trash: {}", err)),
+    }
+    if let Err(err) = store::collect_garbage() {
+        ui::error(&format!("Failed to clean up object store: {}", err));
     }
-    return Some(());
+    ui::lnlnwrite(&format!(
+        "{} Saved as {}",
+        style(Local::now().format("%H:%M:%S")).dim(),
+        style(&save_name).bold()
+    ));
+    return Ok(save_name);
+}
+
+/// Loads the newest quick-save
+fn cmd_quickload(saves_mb: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Result<(), Failure> {
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let (i, _) = saves
+        .iter()
+        .enumerate()
+        .filter(|(_, save)| save.meta.quick)
+        .max_by_key(|(_, save)| save.ctime)
+        .ok_or_else(|| {
+            ui::error("There are no quick-saves");
+            return Failure::NotFound;
+        })?;
+    return cmd_load(saves_mb, Some(&(i + 1).to_string()));
 }
 
-fn cmd_load(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Option<()> {
+/// Saves the current progress into the save it was loaded from, or into a new version of it with `new`
+fn cmd_resave(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    interactive_check_game_closed()?;
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let current_save = SaveInfo::current().ok_or_else(|| {
+        ui::error("There is no current progress to save");
+        return Failure::NotFound;
+    })?;
+    let (index, save, _) = loaded_save(saves, &current_save).ok_or_else(|| {
+        ui::error("The current progress was not loaded from any of the saves");
+        return Failure::NotFound;
+    })?;
+
+    match arg {
+        None if save.meta.pinned => {
+            ui::error(&format!(
+                "Save {} is pinned, unpin it first or use `resave new`",
+                save.name
+            ));
+            return Err(Failure::Validation);
+        }
+        None => {
+            let notes = [style("The save will be replaced with the current progress")
+                .red()
+                .to_string()];
+            if !interactive_preview_and_confirm(
+                "To be overwritten:",
+                &[save],
+                Some(&current_save),
+                &notes,
+                "Overwrite?",
+            )? {
+                return Ok(());
+            }
+            // Store under a visible temporary name first, so the old save is only removed once the new one
+            // is complete, and a leftover of an interrupted resave can be found in the list and deleted
+            let meta = SaveMeta {
+                ctime: Some(save.ctime),
+                ..save.meta.clone()
+            };
+            let tmp_name = utils::unique_name(&format!("{} (resaving)", save.name), |name| {
+                saves.iter().any(|s| s.name == name)
+            });
+            let tmp_path = interactive_store_current(&tmp_name, save_storage(save), meta)?;
+            let replace = || -> io::Result<PathBuf> {
+                utils::delete_dirs_with_progress(&HashSet::from([save.path.as_path()]), Some("Removing old version"))?;
+                let tmp_save = SaveInfo::new(tmp_path.clone()).ok_or(io::ErrorKind::NotFound)?;
+                return utils::rename_save(&tmp_save, &save.name);
+            };
+            let save_path = replace().map_err(|err| {
+                ui::error(&format!(
+                    "Failed to overwrite save: {}\nThe current progress is kept in {}",
+                    err,
+                    tmp_path.display()
+                ));
+                return Failure::Io;
+            })?;
+            if let Err(err) = store::collect_garbage() {
+                ui::error(&format!("Failed to clean up object store: {}", err));
+            }
+            remember_loaded(&save.name);
+            if let Some(save) = SaveInfo::new(save_path) {
+                ui::json_field("save", save.to_json(Some(index), Some(&current_save)));
+            }
+        }
+        Some("new") => {
+            // `Name (2)` becomes `Name (3)` rather than `Name (2) (2)`
+            let base_name = save
+                .name
+                .strip_suffix(')')
+                .and_then(|name| name.rsplit_once(" ("))
+                .filter(|(_, n)| n.parse::<usize>().is_ok())
+                .map_or(save.name.as_str(), |(base_name, _)| base_name);
+            // Always numbered, even if the base name is free
+            let new_name = utils::unique_name(base_name, |name| {
+                name == base_name || saves.iter().any(|s| s.name == name)
+            });
+            interactive_validate_save_name(saves, &new_name)?;
+            let meta = SaveMeta {
+                parent: Some(save.name.clone()),
+                ..SaveMeta::default()
+            };
+            let save_path = interactive_store_current(&new_name, CONFIG.storage, meta)?;
+            remember_loaded(&new_name);
+            if let Some(save) = SaveInfo::new(save_path) {
+                ui::json_field("save", save.to_json(None, Some(&current_save)));
+            }
+        }
+        Some(mode) => {
+            ui::error(&format!("Unknown resave mode: {mode} (expected nothing or \"new\")"));
+            return Err(Failure::Validation);
+        }
+    }
+    return Ok(());
+}
+
+fn cmd_load(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    interactive_check_game_closed()?;
     let saves = interactive_check_saves_mb(saves_mb)?;
     let index = interactive_get_index_or_last(saves, arg)?;
     let save = interactive_get_save_by_index(saves, index)?;
 
-    if let Err(err) = fs::remove_dir_all(&CONFIG.current_save_path) {
-        ui::error(&format!("Failed to delete current progress: {}", err));
-        return None;
+    // Built once, hashing `save00` takes a while
+    let current_save = SaveInfo::current();
+    let notes = unsaved_progress_notes(saves, current_save.as_ref());
+    if !interactive_preview_and_confirm(
+        "To be loaded over the current progress:",
+        &[save],
+        current_save.as_ref(),
+        &notes,
+        "Load?",
+    )? {
+        return Ok(());
     }
-    if let Err(err) =
-        utils::copy_dir_with_progress(&save.path, &CONFIG.current_save_path, true, false, Some("Loading save"))
+    if !interactive_wait_for_cloud_sync()? {
+        return Ok(());
+    }
+
+    load_save(save)?;
+    ui::json_field("loaded", save.to_json(Some(index), SaveInfo::current().as_ref()));
+    return Ok(());
+}
+
+/// Replaces `save00` with `src`, telling whether the current progress survived a failure
+fn interactive_replace_current(src: &Path, progress_bar_title: &str, action: &str) -> Result<(), Failure> {
+    // Whatever a running revert watch expects of `save00` no longer holds
+    REPLACED_CURRENT.fetch_add(1, Ordering::Relaxed);
+    if let Err(err) = utils::replace_dir_with_progress(src, &CONFIG.current_save_path, Some(progress_bar_title)) {
+        if err.untouched {
+            ui::error(&format!(
+                "Failed to {}: {}\nCurrent progress was left untouched",
+                action, err
+            ));
+        } else {
+            ui::error(&format!("Failed to {}: {}", action, err));
+        }
+        return Err(Failure::Io);
+    }
+    return Ok(());
+}
+
+/// Puts back the progress an interrupted load has left aside, so it is backed up before being replaced
+fn interactive_recover_current() -> Result<(), Failure> {
+    if let Err(err) = utils::recover_interrupted_replace(&CONFIG.current_save_path) {
+        ui::error(&format!("Failed to recover after an interrupted load: {}", err));
+        return Err(Failure::Io);
+    }
+    return Ok(());
+}
+
+/// Backs up the current progress and replaces it with `save`
+fn load_save(save: &SaveInfo) -> Result<(), Failure> {
+    interactive_recover_current()?;
+    if CONFIG.current_save_path.exists() {
+        match utils::autosave_current(Some("Backing up current progress")) {
+            Ok(autosave_path) => ui::json_field("autosave", json!(autosave_path.to_string_lossy())),
+            Err(err) => {
+                ui::error(&format!(
+                    "Failed to back up current progress: {}\nNothing was loaded",
+                    err
+                ));
+                return Err(Failure::Io);
+            }
+        }
+        if let Err(err) = utils::rotate_autosaves() {
+            ui::error(&format!("Failed to remove old autosaves: {}", err));
+        }
+    }
+    interactive_replace_current(&save.path, "Loading save", "load save")?;
+    interactive_verify_loaded(save)?;
+    remember_loaded(&save.name);
+    return Ok(());
+}
+
+/// Checks that `save00` has exactly the files of the loaded save.
+/// Only `save00` is hashed, the save is compared by its manifest or its recorded fingerprint
+fn interactive_verify_loaded(save: &SaveInfo) -> Result<(), Failure> {
+    ui::lnlnwrite("Verifying loaded files...").update_later();
+    let report = |err: io::Error| {
+        ui::error(&format!("Failed to verify the loaded files: {}", err));
+        return Failure::Io;
+    };
+    let actual = utils::file_index(&CONFIG.current_save_path).map_err(report)?;
+    if !store::is_manifest(&save.path) {
+        let fingerprint = utils::index_fingerprint(&actual).map_err(report)?;
+        if save.fingerprint() == Some(fingerprint.as_str()) {
+            return Ok(());
+        }
+    }
+    // Files of a dedup save are listed with hashes in its manifest, others are hashed only to explain a mismatch
+    let expected = utils::file_index(&save.path).map_err(report)?;
+    let missing = expected.keys().filter(|path| !actual.contains_key(*path)).count();
+    let extra = actual.keys().filter(|path| !expected.contains_key(*path)).count();
+    let modified = expected
+        .iter()
+        .filter(|(path, file)| actual.get(*path).is_some_and(|a| a.hash != file.hash))
+        .count();
+    if missing + extra + modified > 0 {
+        ui::error(&format!(
+            "Loaded files differ from the save: {missing} missing, {extra} extra, {modified} modified\n\
+             Something has written into the save folder, load the save again or undo with [u]"
+        ));
+        return Err(Failure::Io);
+    }
+    return Ok(());
+}
+
+fn cmd_undo(saves_mb: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Result<(), Failure> {
+    interactive_check_game_closed()?;
+    let autosave = SaveInfo::autosaves().and_then(|autosaves| autosaves.into_iter().last());
+    let autosave = autosave.ok_or_else(|| {
+        ui::error("There is nothing to undo");
+        return Failure::NotFound;
+    })?;
+    let current_save = SaveInfo::current();
+    let notes = unsaved_progress_notes(saves_mb.map_or(&[], |saves| saves.as_slice()), current_save.as_ref());
+    if !interactive_preview_and_confirm(
+        "Autosave to be restored over the current progress:",
+        &[&autosave],
+        current_save.as_ref(),
+        &notes,
+        "Restore?",
+    )? {
+        return Ok(());
+    }
+    if !interactive_wait_for_cloud_sync()? {
+        return Ok(());
+    }
+
+    // Back up current progress too, so the undo can be undone
+    interactive_recover_current()?;
+    if CONFIG.current_save_path.exists()
+        && let Err(err) = utils::autosave_current(Some("Backing up current progress"))
     {
-        ui::error(&format!("Failed to load save: {}", err));
-        return None;
+        ui::error(&format!(
+            "Failed to back up current progress: {}\nNothing was restored",
+            err
+        ));
+        return Err(Failure::Io);
+    }
+    interactive_replace_current(&autosave.path, "Restoring autosave", "restore autosave")?;
+    if let Err(err) = fs::remove_dir_all(&autosave.path).and_then(|_| utils::rotate_autosaves()) {
+        ui::error(&format!("Failed to clean up autosaves: {}", err));
+    }
+    // The restored progress does not come from a save
+    if let Err(err) = State::default().write() {
+        ui::error(&format!("Failed to forget the loaded save: {}", err));
+    }
+    ui::json_field("restored", json!(autosave.name));
+    return Ok(());
+}
+
+/// Leaves out pinned saves with a notice, fails if all of them are pinned
+fn interactive_skip_pinned(saves: &[SaveInfo]) -> Result<Vec<&SaveInfo>, Failure> {
+    let (pinned, unpinned): (Vec<&SaveInfo>, Vec<&SaveInfo>) = saves.iter().partition(|s| s.meta.pinned);
+    if unpinned.is_empty() {
+        ui::error("Pinned saves cannot be deleted, unpin them first");
+        return Err(Failure::Validation);
+    }
+    if !pinned.is_empty() {
+        ui::lnlnwrite(
+            &style(format!(
+                "Skipping pinned saves: {}",
+                pinned.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(", ")
+            ))
+            .yellow()
+            .to_string(),
+        );
+    }
+    return Ok(unpinned);
+}
+
+/// Lists saves a destructive command is about to affect with their total size, `notes` go below them
+fn preview_saves(title: &str, saves: &[&SaveInfo], current_save: Option<&SaveInfo>, notes: &[String]) {
+    let mut lines = vec![style(title).bold().to_string()];
+    for save in saves {
+        lines.push(save.to_string(current_save));
+    }
+    if saves.len() > 1 {
+        let total = saves
+            .iter()
+            .map(|s| s.stat.packed_size.unwrap_or(s.stat.size))
+            .sum::<u64>();
+        lines.push(
+            style(format!("{} saves, {}", saves.len(), ByteSize::b(total)))
+                .dim()
+                .to_string(),
+        );
+    }
+    lines.extend(notes.iter().cloned());
+    ui::lnlnwrite_highlighted(Color::Yellow, &lines.join("\n"));
+}
+
+/// Shows the preview and asks to proceed
+fn interactive_preview_and_confirm(
+    title: &str,
+    saves: &[&SaveInfo],
+    current_save: Option<&SaveInfo>,
+    notes: &[String],
+    question: &str,
+) -> Result<bool, Failure> {
+    preview_saves(title, saves, current_save, notes);
+    let confirmed = interactive_confirm(question)?;
+    if !confirmed {
+        ui::lnlnwrite("Cancelled");
+    }
+    return Ok(confirmed);
+}
+
+/// Warning about the current progress being replaced, empty if it is identical to one of the saves
+fn unsaved_progress_notes(saves: &[SaveInfo], current_save: Option<&SaveInfo>) -> Vec<String> {
+    let Some(current_save) = current_save else {
+        return Vec::new();
+    };
+    if saves.iter().any(|save| save.is_current(Some(current_save))) {
+        return Vec::new();
+    }
+    return vec![
+        style("Current progress is not saved, it will only be kept in autosaves")
+            .red()
+            .to_string(),
+    ];
+}
+
+/// Moves saves to the trash, stops at the first failure
+fn interactive_trash_saves(saves: &[&SaveInfo]) -> Result<(), Failure> {
+    for save in saves {
+        if let Err(err) = utils::trash_save(save) {
+            ui::error(&format!("Failed to move {} to the trash: {}", save.name, err));
+            return Err(Failure::Io);
+        }
+    }
+    return Ok(());
+}
+
+fn cmd_delete(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let index = interactive_get_slice(saves, arg)?;
+    let saves = interactive_skip_pinned(interactive_get_saves_by_slice(saves, index)?)?;
+
+    let notes = [style("Deleted saves go to the trash").dim().to_string()];
+    if !interactive_preview_and_confirm(
+        "To be deleted:",
+        &saves,
+        SaveInfo::current().as_ref(),
+        &notes,
+        "Delete?",
+    )? {
+        return Ok(());
+    }
+    interactive_trash_saves(&saves)?;
+    ui::json_field("deleted", json!(saves.iter().map(|s| &s.name).collect::<Vec<_>>()));
+    return Ok(());
+}
+
+/// `trash` lists deleted saves, `trash restore <index/interval>` brings them back, `trash empty` deletes them for good
+fn cmd_trash(_saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    let trashed = SaveInfo::trashed().ok_or_else(|| {
+        ui::error("Trash is unavailable");
+        return Failure::Io;
+    })?;
+    let (action, action_arg) = match arg {
+        Some(arg) => arg.split_once(' ').map_or((arg, None), |(a, b)| (a, Some(b.trim()))),
+        None => ("list", None),
+    };
+    match action {
+        "list" => {
+            if trashed.is_empty() {
+                ui::lnlnwrite(&style("< Trash is empty >").dim().to_string());
+            } else {
+                ui::lnlnwrite(&format!(
+                    "Trash {}:",
+                    style(format!("(deleted for good after {} days)", CONFIG.trash_days)).dim()
+                ));
+                let i_width = trashed.len().to_string().len();
+                for (i, save) in trashed.iter().enumerate() {
+                    let deleted = save.meta.deleted.map_or("unknown".to_string(), |deleted| {
+                        DateTime::<Local>::from(deleted).format("%b %-d %H:%M:%S").to_string()
+                    });
+                    ui::lnwrite(&format!(
+                        "{:i_width$} ❯ {}  {}",
+                        i + 1,
+                        save.to_string(None),
+                        style(format!("deleted {deleted}")).dim()
+                    ));
+                }
+            }
+            ui::json_field(
+                "trash",
+                trashed
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| {
+                        let mut save = s.to_json(Some(i + 1), None);
+                        save["deleted"] = json!(s.meta.deleted.map(|t| DateTime::<Local>::from(t).to_rfc3339()));
+                        return save;
+                    })
+                    .collect(),
+            );
+        }
+        "restore" => {
+            let slice = interactive_get_slice(&trashed, action_arg)?;
+            let saves = interactive_get_saves_by_slice(&trashed, slice)?;
+            let mut restored = Vec::new();
+            for save in saves {
+                match utils::restore_save(save) {
+                    Ok(path) => restored.push(SaveInfo::new(path).map_or(save.name.clone(), |s| s.name)),
+                    Err(err) => {
+                        ui::error(&format!("Failed to restore {}: {}", save.name, err));
+                        return Err(Failure::Io);
+                    }
+                }
+            }
+            ui::json_field("restored", json!(restored));
+        }
+        "empty" => {
+            if trashed.is_empty() {
+                ui::lnlnwrite(&style("< Trash is empty >").dim().to_string());
+                return Ok(());
+            }
+            let notes = [style("These saves cannot be restored afterwards").red().to_string()];
+            let saves: Vec<&SaveInfo> = trashed.iter().collect();
+            if !interactive_preview_and_confirm(
+                "To be deleted for good:",
+                &saves,
+                SaveInfo::current().as_ref(),
+                &notes,
+                "Empty the trash?",
+            )? {
+                return Ok(());
+            }
+            if let Err(err) = utils::delete_dirs_with_progress(
+                &trashed.iter().map(|s| s.path.as_path()).collect(),
+                Some("Emptying trash"),
+            ) {
+                ui::error(&format!("Failed to empty trash: {}", err));
+                return Err(Failure::Io);
+            }
+            if let Err(err) = store::collect_garbage() {
+                ui::error(&format!("Failed to clean up object store: {}", err));
+            }
+            ui::json_field("deleted", json!(trashed.iter().map(|s| &s.name).collect::<Vec<_>>()));
+        }
+        _ => {
+            ui::error(&format!(
+                "Unknown trash action: {action} (expected \"list\", \"restore\" or \"empty\")"
+            ));
+            return Err(Failure::Validation);
+        }
     }
-    return Some(());
+    return Ok(());
 }
 
-fn cmd_delete(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Option<()> {
+fn set_pinned(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>, pinned: bool) -> Result<(), Failure> {
     let saves = interactive_check_saves_mb(saves_mb)?;
     let index = interactive_get_slice(saves, arg)?;
     let saves = interactive_get_saves_by_slice(saves, index)?;
 
-    if let Err(err) =
-        utils::delete_dirs_with_progress(&saves.iter().map(|s| s.path.as_path()).collect(), Some("Deleting save"))
-    {
-        ui::error(&format!("Failed to delete save: {}", err));
-        return None;
+    for save in saves {
+        let meta = SaveMeta {
+            pinned,
+            ..save.meta.clone()
+        };
+        if let Err(err) = meta.write(&save.path) {
+            ui::error(&format!("Failed to update {}: {}", save.name, err));
+            return Err(Failure::Io);
+        }
+    }
+    ui::json_field(
+        if pinned { "pinned" } else { "unpinned" },
+        json!(saves.iter().map(|s| &s.name).collect::<Vec<_>>()),
+    );
+    return Ok(());
+}
+
+/// Protects saves from delete, prune and quick-save rotation
+fn cmd_pin(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    return set_pinned(saves_mb, arg, true);
+}
+
+fn cmd_unpin(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    return set_pinned(saves_mb, arg, false);
+}
+
+/// Deletes saves by the retention rules from the config, `prune dry` only shows what would go
+fn cmd_prune(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let dry_run = match arg {
+        None => false,
+        Some("dry") => true,
+        Some(mode) => {
+            ui::error(&format!("Unknown prune mode: {mode} (expected nothing or \"dry\")"));
+            return Err(Failure::Validation);
+        }
+    };
+    let pruned = prune::plan(saves, &prune::Rules::from_config());
+    ui::json_field("dry_run", json!(dry_run));
+    ui::json_field("pruned", json!(pruned.iter().map(|s| &s.name).collect::<Vec<_>>()));
+    if pruned.is_empty() {
+        ui::lnlnwrite("Nothing to prune");
+        return Ok(());
+    }
+
+    if dry_run {
+        preview_saves("Would be deleted:", &pruned, SaveInfo::current().as_ref(), &[]);
+        return Ok(());
+    }
+    let notes = [style("Deleted saves go to the trash").dim().to_string()];
+    if !interactive_preview_and_confirm(
+        "To be deleted:",
+        &pruned,
+        SaveInfo::current().as_ref(),
+        &notes,
+        "Delete?",
+    )? {
+        return Ok(());
+    }
+
+    return interactive_trash_saves(&pruned);
+}
+
+fn cmd_copy(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let (str_index, name_arg) = interactive_split_index_and_name(arg)?;
+    let index = interactive_parse_index(&str_index)?;
+    let save = interactive_get_save_by_index(saves, index)?;
+    let new_name = interactive_get_save_name(name_arg)?;
+    interactive_validate_save_name(saves, &new_name)?;
+
+    match utils::copy_save(save, &new_name, Some("Copying save")) {
+        Ok(path) => {
+            if let Some(copy) = SaveInfo::new(path) {
+                ui::json_field("save", copy.to_json(None, None));
+            }
+        }
+        Err(err) => {
+            ui::error(&format!("Failed to copy save: {}", err));
+            return Err(Failure::Io);
+        }
+    }
+    return Ok(());
+}
+
+fn cmd_rename(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let (str_index, name_arg) = interactive_split_index_and_name(arg)?;
+    let index = interactive_parse_index(&str_index)?;
+    let save = interactive_get_save_by_index(saves, index)?;
+    let new_name = interactive_get_save_name(name_arg)?;
+    interactive_validate_save_name(saves, &new_name)?;
+
+    if let Err(err) = utils::rename_save(save, &new_name) {
+        ui::error(&format!("Failed to rename save: {}", err));
+        return Err(Failure::Io);
+    }
+    // Keep copies attached to the renamed save
+    for child in saves.iter().filter(|s| s.meta.parent.as_ref() == Some(&save.name)) {
+        let mut meta = SaveMeta::read(&child.path);
+        meta.parent = Some(new_name.clone());
+        if let Err(err) = meta.write(&child.path) {
+            ui::error(&format!("Failed to update parent of {}: {}", child.name, err));
+        }
+    }
+    let mut state = State::read();
+    if state.loaded.as_ref() == Some(&save.name) {
+        state.loaded = Some(new_name.clone());
+        if let Err(err) = state.write() {
+            ui::error(&format!("Failed to update the loaded save: {}", err));
+        }
+    }
+    ui::json_field("renamed", json!({"from": save.name, "to": new_name}));
+    return Ok(());
+}
+
+/// Sizes of the top-level directories of a save, the main ones are always listed
+fn subdir_sizes(files: &[(String, u64)]) -> Vec<(String, u64)> {
+    let mut sizes: Vec<(String, u64)> = ["world", "persistent", "stats"]
+        .iter()
+        .map(|dir| (format!("{dir}/"), 0))
+        .collect();
+    let mut other = 0;
+    for (path, size) in files {
+        let Some((dir, _)) = path.split_once('/') else {
+            other += size;
+            continue;
+        };
+        let dir = format!("{dir}/");
+        match sizes.iter_mut().find(|(name, _)| *name == dir) {
+            Some((_, total)) => *total += size,
+            None => sizes.push((dir, *size)),
+        }
+    }
+    sizes.push(("other files".to_string(), other));
+    return sizes;
+}
+
+fn cmd_info(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let index = interactive_get_index_or_last(saves, arg)?;
+    let save = interactive_get_save_by_index(saves, index)?;
+    let format_time = |time: SystemTime| DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string();
+    let mtime = save.mtime();
+
+    let mut lines = vec![
+        style(&save.name).bold().to_string(),
+        format!("Path: {}", save.path.display()),
+        format!("Created: {}", format_time(save.ctime)),
+        format!("Modified: {}", mtime.map_or("unknown".to_string(), format_time)),
+    ];
+    if let Some(parent) = &save.meta.parent {
+        lines.push(format!("Copied from: {parent}"));
+    }
+    ui::lnlnwrite_highlighted(Color::Cyan, &lines.join("\n"));
+
+    let files = utils::save_files(&save.path).unwrap_or_else(|err| {
+        ui::error(&format!("Failed to list files of the save: {}", err));
+        return Vec::new();
+    });
+    let sizes = subdir_sizes(&files);
+    let cache_stale = save.is_cache_stale();
+    let mut lines = vec![
+        style("Contents").bold().to_string(),
+        format!("Size: {} in {} files", ByteSize::b(save.stat.size), save.stat.count),
+    ];
+    if let Some(packed_size) = save.stat.packed_size {
+        lines.push(format!("Packed size: {}", ByteSize::b(packed_size)));
+    }
+    for (dir, size) in &sizes {
+        lines.push(format!("  {dir}: {}", ByteSize::b(*size)));
+    }
+    lines.push(match cache_stale {
+        Some(false) => "Cache: up to date".to_string(),
+        Some(true) => style("Cache: stale").yellow().to_string(),
+        None => style("Cache: missing").yellow().to_string(),
+    });
+    ui::lnlnwrite_highlighted(Color::Cyan, &lines.join("\n"));
+
+    let mut lines = vec![style("Player").bold().to_string()];
+    match &save.meta.player {
+        Some(player) => {
+            lines.push(format!("HP: {:.0} / {:.0}", player.hp, player.max_hp));
+            lines.push(format!("Gold: {}", player.gold));
+            lines.push(format!("Position: x {:.0}, y {:.0}", player.x, player.y));
+            for (i, wand) in player.wands.iter().enumerate() {
+                lines.push(format!(
+                    "Wand {}: {} {}",
+                    i + 1,
+                    wand.name,
+                    style(format!("[{}]", wand.spells.join(", "))).dim()
+                ));
+            }
+        }
+        None => lines.push(
+            style("No player data (the save was made by an older version)")
+                .dim()
+                .to_string(),
+        ),
+    }
+    ui::lnlnwrite_highlighted(Color::Cyan, &lines.join("\n"));
+
+    if let Some(run) = &save.meta.run {
+        let mut lines = vec![
+            style("Run").bold().to_string(),
+            format!(
+                "Seed: {}",
+                run.seed.map_or("unknown".to_string(), |seed| seed.to_string())
+            ),
+            format!("Play time: {}", run.play_time_string()),
+            format!("Orbs: {}", run.orbs),
+            format!("Kills: {}", run.kills),
+        ];
+        if !run.perks.is_empty() {
+            lines.push(format!("Perks: {}", run.perks.join(", ")));
+        }
+        if run.ended {
+            let reason = run
+                .killed_by
+                .as_ref()
+                .map_or(String::new(), |by| format!(" (killed by {by})"));
+            lines.push(style(format!("Run ended{reason}")).red().to_string());
+        }
+        ui::lnlnwrite_highlighted(Color::Cyan, &lines.join("\n"));
+    }
+
+    ui::json_field("save", save.to_json(Some(index), SaveInfo::current().as_ref()));
+    ui::json_field("mtime", json!(mtime.map(|t| DateTime::<Local>::from(t).to_rfc3339())));
+    ui::json_field(
+        "subdirs",
+        sizes
+            .iter()
+            .map(|(dir, size)| json!({"name": dir, "size": size}))
+            .collect(),
+    );
+    ui::json_field("cache_stale", json!(cache_stale));
+    return Ok(());
+}
+
+/// One side of a diff: a save or the current progress
+struct DiffSide {
+    label: String,
+    path: PathBuf,
+    player: Option<PlayerInfo>,
+    run: Option<RunInfo>,
+}
+
+impl DiffSide {
+    fn save(save: &SaveInfo, index: usize) -> DiffSide {
+        DiffSide {
+            label: format!("#{index} {}", save.name),
+            path: save.path.clone(),
+            player: save.meta.player.clone(),
+            run: save.meta.run.clone(),
+        }
+    }
+
+    fn current() -> DiffSide {
+        DiffSide {
+            label: "current progress".to_string(),
+            path: CONFIG.current_save_path.clone(),
+            player: PlayerInfo::read(&CONFIG.current_save_path),
+            run: RunInfo::read(&CONFIG.current_save_path),
+        }
+    }
+
+    fn interactive_file_index(&self) -> Result<BTreeMap<String, IndexedFile>, Failure> {
+        utils::file_index(&self.path).map_err(|err| {
+            ui::error(&format!("Failed to read files of {}: {}", self.label, err));
+            return Failure::Io;
+        })
+    }
+}
+
+fn signed_size(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{sign}{}", ByteSize::b(delta.unsigned_abs()))
+}
+
+fn cmd_diff(saves_mb: Option<&Vec<SaveInfo>>, arg: Option<&str>) -> Result<(), Failure> {
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let arg = match arg {
+        Some(arg) => arg.to_string(),
+        None => interactive_ask("Save index (and the second one, current progress if missing)")?,
+    };
+    let mut str_indices = arg.split_whitespace();
+    let old_index = interactive_get_index_or_last(saves, str_indices.next())?;
+    let old = DiffSide::save(interactive_get_save_by_index(saves, old_index)?, old_index);
+    let new = match str_indices.next() {
+        Some(str_index) => {
+            let new_index = interactive_parse_index(str_index)?;
+            DiffSide::save(interactive_get_save_by_index(saves, new_index)?, new_index)
+        }
+        None => DiffSide::current(),
+    };
+
+    ui::lnlnwrite("Comparing files...").update_later();
+    let old_files = old.interactive_file_index()?;
+    let new_files = new.interactive_file_index()?;
+    let mut lines = vec![style(format!("{} ❯ {}", old.label, new.label)).bold().to_string()];
+    let (mut added, mut removed, mut modified, mut unchanged) = (Vec::new(), Vec::new(), Vec::new(), 0);
+    for (path, old_file) in &old_files {
+        match new_files.get(path) {
+            None => {
+                lines.push(
+                    style(format!("- {path} ({})", ByteSize::b(old_file.size)))
+                        .red()
+                        .to_string(),
+                );
+                removed.push(json!({"path": path, "size": old_file.size}));
+            }
+            Some(new_file) if new_file != old_file => {
+                let delta = new_file.size as i64 - old_file.size as i64;
+                lines.push(style(format!("~ {path} ({})", signed_size(delta))).yellow().to_string());
+                modified
+                    .push(json!({"path": path, "old_size": old_file.size, "new_size": new_file.size, "delta": delta}));
+            }
+            Some(_) => unchanged += 1,
+        }
+    }
+    for (path, new_file) in new_files.iter().filter(|(path, _)| !old_files.contains_key(*path)) {
+        lines.push(
+            style(format!("+ {path} ({})", ByteSize::b(new_file.size)))
+                .green()
+                .to_string(),
+        );
+        added.push(json!({"path": path, "size": new_file.size}));
     }
-    return Some(());
+    let total_delta =
+        new_files.values().map(|f| f.size as i64).sum::<i64>() - old_files.values().map(|f| f.size as i64).sum::<i64>();
+    lines.push(
+        style(format!(
+            "{} added, {} removed, {} modified, {} unchanged, {} in total",
+            added.len(),
+            removed.len(),
+            modified.len(),
+            unchanged,
+            signed_size(total_delta)
+        ))
+        .dim()
+        .to_string(),
+    );
+    ui::lnlnwrite_highlighted(Color::Cyan, &lines.join("\n"));
+
+    let changes = game::game_changes(
+        (old.player.as_ref(), old.run.as_ref()),
+        (new.player.as_ref(), new.run.as_ref()),
+    );
+    let mut lines = vec![style("Game state").bold().to_string()];
+    if old.player.is_none() && old.run.is_none() || new.player.is_none() && new.run.is_none() {
+        lines.push(
+            style("No game data to compare (a save was made by an older version)")
+                .dim()
+                .to_string(),
+        );
+    } else if changes.is_empty() {
+        lines.push(style("No changes").dim().to_string());
+    }
+    for change in &changes {
+        lines.push(format!(
+            "{}: {} {} {}",
+            change.field,
+            style(&change.from).red(),
+            style("❯").dim(),
+            style(&change.to).green()
+        ));
+    }
+    ui::lnlnwrite_highlighted(Color::Cyan, &lines.join("\n"));
+
+    ui::json_field("from", json!(old.label));
+    ui::json_field("to", json!(new.label));
+    ui::json_field(
+        "files",
+        json!({"added": added, "removed": removed, "modified": modified, "unchanged": unchanged, "delta": total_delta}),
+    );
+    ui::json_field("game", json!(changes));
+    return Ok(());
 }
 
-fn cmd_play(_saves_mb: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Option<()> {
+fn cmd_play(saves_mb: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Result<(), Failure> {
+    if !interactive_wait_for_cloud_sync()? {
+        return Ok(());
+    }
     ui::lnlnwrite("Launching Noita...").update_later();
-    open::that("steam://rungameid/881100").ok()
+    open::that("steam://rungameid/881100").map_err(|err| {
+        ui::error(&format!("Failed to launch Noita: {}", err));
+        return Failure::Io;
+    })?;
+    if ui::is_interactive() {
+        start_revert_watch(saves_mb);
+    }
+    return Ok(());
+}
+
+/// Modification marker of `save00`: size, files count and the latest modification time
+fn current_save_marker() -> Option<(u64, usize, Option<SystemTime>)> {
+    let current = SaveInfo::current()?;
+    return Some((current.stat.size, current.stat.count, current.mtime()));
 }
 
-fn cmd_quit(_saves: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Option<()> {
+/// Set while a background watch started by `play` is running
+static WATCHING_REVERT: AtomicBool = AtomicBool::new(false);
+/// Name of the save whose load Steam Cloud has reverted, reapplying is offered at the next prompt
+static REVERTED_LOAD: Mutex<Option<String>> = Mutex::new(None);
+/// Bumped whenever a command replaces `save00`, ends the revert watch started before
+static REPLACED_CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Watches `save00` in the background for `revert_watch` seconds after the launch:
+/// Steam Cloud may replace a freshly loaded save with the progress backed up before the load
+fn start_revert_watch(saves_mb: Option<&Vec<SaveInfo>>) {
+    if CONFIG.revert_watch == 0 || steam::cloud_sync_state().is_none() {
+        return;
+    }
+    let (Some(saves), Some(current)) = (saves_mb, SaveInfo::current()) else {
+        return;
+    };
+    let Some((_, save, false)) = loaded_save(saves, &current) else {
+        return;
+    };
+    let Some(loaded) = save.fingerprint().map(str::to_string) else {
+        return;
+    };
+    if WATCHING_REVERT.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let name = save.name.clone();
+    let generation = REPLACED_CURRENT.load(Ordering::Relaxed);
+    thread::spawn(move || {
+        if is_load_reverted(&loaded, generation) {
+            ui::lnlnwrite_highlighted(
+                Color::Red,
+                &format!(
+                    "Load reverted by Steam sync\n\
+                     The current progress is back to the one from before loading {name}\n\
+                     Press Enter to reapply the load"
+                ),
+            );
+            *REVERTED_LOAD.lock().unwrap() = Some(name);
+        }
+        WATCHING_REVERT.store(false, Ordering::Relaxed);
+    });
+}
+
+/// Whether `save00` with `loaded` fingerprint turns back into the latest autosave within `revert_watch` seconds,
+/// gives up as soon as a command replaces `save00` after `generation`
+fn is_load_reverted(loaded: &str, generation: usize) -> bool {
+    let replaced = || REPLACED_CURRENT.load(Ordering::Relaxed) != generation;
+    let previous = SaveInfo::autosaves().and_then(|autosaves| autosaves.into_iter().last());
+    let Some(previous) = previous.as_ref().and_then(|autosave| autosave.fingerprint()) else {
+        return false;
+    };
+
+    let started = Instant::now();
+    let watch = Duration::from_secs(CONFIG.revert_watch as u64);
+    let mut marker = current_save_marker();
+    let mut changed = false;
+    while started.elapsed() < watch {
+        thread::sleep(Duration::from_secs(2));
+        if replaced() {
+            return false;
+        }
+        let new_marker = current_save_marker();
+        if new_marker != marker {
+            // Still being written, check once the files settle
+            marker = new_marker;
+            changed = true;
+            continue;
+        }
+        if !changed {
+            continue;
+        }
+        changed = false;
+        let fingerprint = utils::fingerprint(&CONFIG.current_save_path).ok();
+        if fingerprint.as_deref() == Some(loaded) {
+            continue;
+        }
+        // Anything else is the progress written by the game itself
+        return fingerprint.as_deref() == Some(previous) && !replaced();
+    }
+    return false;
+}
+
+/// Offers to load the save again if the background watch has seen Steam Cloud reverting its load
+pub fn interactive_reapply_reverted_load() -> Result<(), Failure> {
+    let Some(name) = REVERTED_LOAD.lock().unwrap().take() else {
+        return Ok(());
+    };
+    let saves = SaveInfo::all().unwrap_or_default();
+    let Some((i, save)) = saves.iter().enumerate().find(|(_, save)| save.name == name) else {
+        return Ok(());
+    };
+    if !interactive_confirm(&format!(
+        "Quit the game and reapply the load of #{} {}?",
+        i + 1,
+        save.name
+    ))? {
+        ui::lnlnwrite("Cancelled");
+        return Ok(());
+    }
+    if CONFIG.check_game_running {
+        while let Ok(true) = processes::is_noita_running() {
+            ui::lnlnwrite("Waiting for Noita to close...").update_later();
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+    // Asks to make sure the game is closed if processes cannot be listed
+    interactive_check_game_closed()?;
+    if !interactive_wait_for_cloud_sync()? {
+        return Ok(());
+    }
+    load_save(save)?;
+    ui::lnlnwrite_highlighted(Color::Green, "The load is reapplied, launch the game with [p]");
+    return Ok(());
+}
+
+/// `Saves:` with the Steam Cloud state if it is used
+pub fn saves_header() -> String {
+    match steam::cloud_sync_state() {
+        Some(CloudSync::Idle) => format!("Saves: {}", style("(Steam Cloud: idle)").dim()),
+        Some(state) => format!("Saves: {}", style(format!("(Steam Cloud: {state})")).yellow()),
+        None => "Saves:".to_string(),
+    }
+}
+
+/// Save the current progress was loaded from: index, save and whether the progress has changed since
+fn loaded_save<'a>(saves: &'a [SaveInfo], current_save: &SaveInfo) -> Option<(usize, &'a SaveInfo, bool)> {
+    let name = State::read().loaded?;
+    let (i, save) = saves.iter().enumerate().find(|(_, save)| save.name == name)?;
+    return Some((i + 1, save, !save.is_current(Some(current_save))));
+}
+
+/// In-game time played in the current progress since it was saved in `save`, in seconds
+fn played_since(save: &SaveInfo) -> Option<u64> {
+    let current_run = RunInfo::read(&CONFIG.current_save_path)?;
+    let save_run = save.meta.run.as_ref()?;
+    if current_run.seed != save_run.seed || current_run.play_time < save_run.play_time {
+        return None;
+    }
+    return Some((current_run.play_time - save_run.play_time) as u64);
+}
+
+/// `23 min` or `1 h 05 min`
+fn duration_string(secs: u64) -> String {
+    let minutes = secs / 60;
+    if minutes < 60 {
+        format!("{minutes} min")
+    } else {
+        format!("{} h {:02} min", minutes / 60, minutes % 60)
+    }
+}
+
+/// Prints saves list with autosaves below it
+pub fn print_saves(saves_mb: Option<&Vec<SaveInfo>>, current_save: Option<&SaveInfo>, autosaves: &[SaveInfo]) {
+    if let Some(saves) = saves_mb {
+        if !saves.is_empty() {
+            let i_width = saves.len().to_string().len();
+            for (i, save) in saves.iter().enumerate() {
+                ui::lnwrite(&format!("{:i_width$} ❯ {}", i + 1, save.to_string(current_save)));
+            }
+            if let Some((index, save, true)) = current_save.and_then(|current| loaded_save(saves, current)) {
+                let since = match played_since(save) {
+                    Some(played) => format!(" (played {} since)", duration_string(played)),
+                    None => State::read()
+                        .loaded_at
+                        .and_then(|loaded_at| loaded_at.elapsed().ok())
+                        .map_or(String::new(), |elapsed| {
+                            format!(" (loaded {} ago)", duration_string(elapsed.as_secs()))
+                        }),
+                };
+                ui::lnwrite(
+                    &style(format!("Current progress derived from #{index} {}{since}", save.name))
+                        .yellow()
+                        .to_string(),
+                );
+                ui::lnwrite(
+                    &style("Enter `resave` to overwrite that save or `resave new` to save a new version of it")
+                        .dim()
+                        .to_string(),
+                );
+            }
+        } else {
+            ui::lnlnwrite(&style("< Nothing >").dim().to_string());
+        }
+    } else {
+        ui::error("Cannot load saves");
+    }
+
+    // The last autosave is restored by undo
+    if !autosaves.is_empty() {
+        ui::lnlnwrite(&style("Autosaves:").dim().to_string());
+        for autosave in autosaves {
+            ui::lnwrite(&format!("{} {}", style("↶").dim(), autosave.to_string(current_save)));
+        }
+    }
+}
+
+fn cmd_list(saves_mb: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Result<(), Failure> {
+    let saves = interactive_check_saves_mb(saves_mb)?;
+    let current_save = SaveInfo::current();
+    let autosaves = SaveInfo::autosaves().unwrap_or_default();
+    ui::lnlnwrite(&saves_header());
+    print_saves(Some(saves), current_save.as_ref(), &autosaves);
+
+    let current = current_save.as_ref();
+    ui::json_field(
+        "saves",
+        saves
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s.to_json(Some(i + 1), current))
+            .collect(),
+    );
+    ui::json_field(
+        "cloud_sync",
+        json!(steam::cloud_sync_state().map(|state| state.to_string())),
+    );
+    ui::json_field(
+        "autosaves",
+        autosaves.iter().map(|s| s.to_json(None, current)).collect(),
+    );
+    ui::json_field(
+        "current",
+        current.map_or(json!(null), |c| {
+            let loaded_at = State::read().loaded_at.map(|t| DateTime::<Local>::from(t).to_rfc3339());
+            let loaded = loaded_save(saves, c).map(|(index, save, modified)| {
+                json!({
+                    "index": index,
+                    "name": save.name,
+                    "modified": modified,
+                    "loaded_at": loaded_at,
+                    "played_since": played_since(save),
+                })
+            });
+            json!({"size": c.stat.size, "count": c.stat.count, "loaded_from": loaded})
+        }),
+    );
+    return Ok(());
+}
+
+fn cmd_quit(_saves: Option<&Vec<SaveInfo>>, _arg: Option<&str>) -> Result<(), Failure> {
     ui::lnlnwrite("Thx for using NoitaSaves! Have a nice day!\n");
-    return Some(());
+    return Ok(());
 }
 
+#[cfg(windows)]
 enum XAction {
     Create,
     Remove,
 }
 
+#[cfg(windows)]
 enum XLocation {
     Desktop,
     StartMenu,
 }
 
-fn cmd_x(_saves: Option<&Vec<SaveInfo>>, arg_mb: Option<&str>) -> Option<()> {
+#[cfg(windows)]
+fn cmd_x(_saves: Option<&Vec<SaveInfo>>, arg_mb: Option<&str>) -> Result<(), Failure> {
     let arg = arg_mb.map(|s| s.to_string()).or_else(|| {
         ui::lnlnwrite(
             ui::dim_squares(
@@ -228,12 +1627,14 @@ fn cmd_x(_saves: Option<&Vec<SaveInfo>>, arg_mb: Option<&str>) -> Option<()> {
                     |rd ❯ [R]emove from [D]esktop
                     |rs ❯ [R]emove from [S]tart Menu
                 "
-                .trim_margin()?,
+                .trim_margin()
+                .unwrap_or_default(),
             )
             .as_str(),
         );
         return ui::ask("Action").map(|s| s.to_lowercase());
-    })?;
+    });
+    let arg = arg.ok_or(Failure::Validation)?;
 
     let (action, location) = match arg.as_str() {
         "cd" => (XAction::Create, XLocation::Desktop),
@@ -242,17 +1643,17 @@ fn cmd_x(_saves: Option<&Vec<SaveInfo>>, arg_mb: Option<&str>) -> Option<()> {
         "rs" => (XAction::Remove, XLocation::StartMenu),
         _ => {
             ui::error(&format!("Invalid mode: {}", arg));
-            return None;
+            return Err(Failure::Validation);
         }
     };
 
     let shortcut_path = match location {
         XLocation::Desktop => {
-            let home = env::var("USERPROFILE").ok()?;
+            let home = env::var("USERPROFILE").map_err(|_| Failure::Io)?;
             PathBuf::from(home).join("Desktop").join("NoitaSaves.lnk")
         }
         XLocation::StartMenu => {
-            let appdata = env::var("APPDATA").ok()?;
+            let appdata = env::var("APPDATA").map_err(|_| Failure::Io)?;
             PathBuf::from(appdata)
                 .join("Microsoft")
                 .join("Windows")
@@ -262,39 +1663,88 @@ fn cmd_x(_saves: Option<&Vec<SaveInfo>>, arg_mb: Option<&str>) -> Option<()> {
         }
     };
 
-    let exe_path = env::current_exe().ok()?;
+    let exe_path = env::current_exe().map_err(|_| Failure::Io)?;
     let shortcut = Shortcut::new(exe_path);
 
-    match action {
-        XAction::Create => {
-            shortcut.save(shortcut_path).ok()?;
-        }
-        XAction::Remove => fs::remove_file(shortcut_path).ok()?,
+    let result = match action {
+        XAction::Create => shortcut.save(shortcut_path).map_err(|err| err.to_string()),
+        XAction::Remove => fs::remove_file(shortcut_path).map_err(|err| err.to_string()),
+    };
+    if let Err(err) = result {
+        ui::error(&format!("Failed to update the shortcut: {}", err));
+        return Err(Failure::Io);
     }
 
     ui::lnlnwrite("Done!");
-    return Some(());
+    return Ok(());
 }
 
-pub fn cmd_not_found(cmd: &str) {
+pub fn cmd_not_found(cmd: &str) -> Failure {
     ui::error(&format!("No such command: \"{}\"", cmd));
+    return Failure::Validation;
 }
 
-pub static CMD_MAP: LazyLock<HashMap<&str, fn(Option<&Vec<SaveInfo>>, Option<&str>) -> Option<()>>> =
-    LazyLock::new(|| {
-        let mut map = HashMap::from([
-            ("x", cmd_x as fn(Option<&Vec<SaveInfo>>, Option<&str>) -> Option<()>),
-            ("save", cmd_save),
-            ("load", cmd_load),
-            ("delete", cmd_delete),
-            ("play", cmd_play),
-            ("quit", cmd_quit),
-        ]);
-        if DEBUG {
-            map.insert("test", cmd_test);
-        }
-        return map;
-    });
+pub type Command = fn(Option<&Vec<SaveInfo>>, Option<&str>) -> Result<(), Failure>;
 
-pub static CMD_SHORTCUTS: LazyLock<HashMap<&str, &str>> =
-    LazyLock::new(|| CMD_MAP.keys().map(|&cmd_name| (&cmd_name[..1], cmd_name)).collect());
+pub static CMD_MAP: LazyLock<HashMap<&str, Command>> = LazyLock::new(|| {
+    let mut map = HashMap::from([
+        ("save", cmd_save as Command),
+        ("load", cmd_load),
+        ("undo", cmd_undo),
+        ("delete", cmd_delete),
+        ("list", cmd_list),
+        ("rename", cmd_rename),
+        ("copy", cmd_copy),
+        ("info", cmd_info),
+        ("resave", cmd_resave),
+        ("quicksave", cmd_quicksave),
+        ("quickload", cmd_quickload),
+        ("watch", cmd_watch),
+        ("prune", cmd_prune),
+        ("pin", cmd_pin),
+        ("unpin", cmd_unpin),
+        ("trash", cmd_trash),
+        ("diff", cmd_diff),
+        ("play", cmd_play),
+        ("quit", cmd_quit),
+    ]);
+    #[cfg(windows)]
+    map.insert("x", cmd_x);
+    if *DEBUG {
+        map.insert("test", cmd_test);
+    }
+    return map;
+});
+
+pub static CMD_SHORTCUTS: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
+    HashMap::from([
+        ("s", "save"),
+        ("l", "load"),
+        ("u", "undo"),
+        ("d", "delete"),
+        ("r", "rename"),
+        ("c", "copy"),
+        ("i", "info"),
+        ("qs", "quicksave"),
+        ("ql", "quickload"),
+        ("p", "play"),
+        ("q", "quit"),
+        ("t", "test"),
+    ])
+    .into_iter()
+    .filter(|(_, cmd_name)| CMD_MAP.contains_key(cmd_name))
+    .collect()
+});
+
+/// Resolves command name or its shortcut and calls the command
+pub fn run(saves_mb: Option<&Vec<SaveInfo>>, cmd_name_or_alias: &str, arg: Option<&str>) -> Result<(), Failure> {
+    let cmd_name = CMD_SHORTCUTS
+        .get(cmd_name_or_alias)
+        .copied()
+        .unwrap_or(cmd_name_or_alias);
+    if let Some(cmd) = CMD_MAP.get(cmd_name) {
+        return cmd(saves_mb, arg);
+    } else {
+        return Err(cmd_not_found(cmd_name_or_alias));
+    }
+}
@@ -1,5 +1,13 @@
-use std::{env, path::PathBuf, sync::LazyLock};
+use bytesize::ByteSize;
+use std::{
+    cell::LazyCell,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
 
+#[cfg(not(windows))]
+use crate::steam;
 use crate::ui;
 
 #[derive(Debug)]
@@ -7,32 +15,307 @@ pub struct Config {
     pub cache_file_name: String,
     pub saves_dir_path: PathBuf,
     pub current_save_path: PathBuf,
+    pub autosaves_dir_path: PathBuf,
+    pub autosaves_limit: usize,
+    pub quicksaves_limit: usize,
+    /// Saves of `watch` kept, the oldest ones go to the trash
+    pub watch_saves_limit: usize,
+    pub prune_keep_last: usize,
+    pub prune_keep_days: usize,
+    pub prune_max_size: Option<u64>,
+    pub objects_dir_path: PathBuf,
+    pub trash_dir_path: PathBuf,
+    pub trash_days: usize,
+    pub state_file_path: PathBuf,
+    pub storage: Storage,
+    /// Refuse to touch `save00` while the game is running
+    pub check_game_running: bool,
+    /// Process table read to find the game on Linux
+    #[cfg(not(windows))]
+    pub proc_root: PathBuf,
+    /// How long to wait for Steam Cloud sync before loading or launching the game, in seconds
+    pub steam_sync_wait: usize,
+    /// How long to watch `save00` after launching the game for Steam Cloud bringing back the old progress, in seconds
+    pub revert_watch: usize,
 }
 
-pub static DEBUG: bool = false;
-static DEBUG_LOCATION: bool = false;
+/// How new saves are stored
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Storage {
+    /// Plain copy of `save00`
+    Dir,
+    /// Single `.tar.zst` archive
+    Archive,
+    /// Manifest in the deduplicated object store
+    Dedup,
+}
 
-pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
-    let common_location;
-    if !DEBUG_LOCATION {
-        if let Ok(appdata) = env::var("APPDATA") {
-            common_location = PathBuf::from(appdata.replace("Roaming", "LocalLow"));
-        } else {
-            ui::error("Failed to get APPDATA environment variable");
-            panic!();
+/// Optional values from the config file and `NOITA_SAVES_*` environment variables
+#[derive(Debug, Default)]
+struct Overrides {
+    cache_file_name: Option<String>,
+    saves_dir_path: Option<PathBuf>,
+    current_save_path: Option<PathBuf>,
+    autosaves_limit: Option<usize>,
+    quicksaves_limit: Option<usize>,
+    watch_saves_limit: Option<usize>,
+    prune_keep_last: Option<usize>,
+    prune_keep_days: Option<usize>,
+    prune_max_size: Option<u64>,
+    trash_days: Option<usize>,
+    storage: Option<Storage>,
+    check_game_running: Option<bool>,
+    #[cfg(not(windows))]
+    proc_root: Option<PathBuf>,
+    steam_sync_wait: Option<usize>,
+    revert_watch: Option<usize>,
+    debug: Option<bool>,
+    debug_location: Option<bool>,
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(format!("expected true or false, got \"{value}\"")),
+    }
+}
+
+fn parse_storage(value: &str) -> Result<Storage, String> {
+    match value.to_lowercase().as_str() {
+        "dir" => Ok(Storage::Dir),
+        "archive" => Ok(Storage::Archive),
+        "dedup" => Ok(Storage::Dedup),
+        _ => Err(format!("expected \"dir\", \"archive\" or \"dedup\", got \"{value}\"")),
+    }
+}
+
+fn parse_size(value: &str) -> Result<u64, String> {
+    value
+        .parse::<ByteSize>()
+        .map(|size| size.as_u64())
+        .map_err(|_| format!("expected a size like \"10 GB\", got \"{value}\""))
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a non-negative number, got \"{value}\""))
+}
+
+impl Overrides {
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "cache_file_name" => self.cache_file_name = Some(value.to_string()),
+            "saves_dir_path" => self.saves_dir_path = Some(PathBuf::from(value)),
+            "current_save_path" => self.current_save_path = Some(PathBuf::from(value)),
+            "autosaves_limit" => self.autosaves_limit = Some(parse_number(value)?),
+            "quicksaves_limit" => self.quicksaves_limit = Some(parse_number(value)?),
+            "watch_saves_limit" => self.watch_saves_limit = Some(parse_number(value)?),
+            "prune_keep_last" => self.prune_keep_last = Some(parse_number(value)?),
+            "prune_keep_days" => self.prune_keep_days = Some(parse_number(value)?),
+            "prune_max_size" => self.prune_max_size = Some(parse_size(value)?),
+            "trash_days" => self.trash_days = Some(parse_number(value)?),
+            "storage" => self.storage = Some(parse_storage(value)?),
+            "check_game_running" => self.check_game_running = Some(parse_bool(value)?),
+            #[cfg(not(windows))]
+            "proc_root" => self.proc_root = Some(PathBuf::from(value)),
+            "steam_sync_wait" => self.steam_sync_wait = Some(parse_number(value)?),
+            "revert_watch" => self.revert_watch = Some(parse_number(value)?),
+            "debug" => self.debug = Some(parse_bool(value)?),
+            "debug_location" => self.debug_location = Some(parse_bool(value)?),
+            _ => return Err("unknown key".to_string()),
         }
-    } else {
-        if let Ok(home) = env::var("USERPROFILE") {
-            common_location = PathBuf::from(home).join("tmp").join("noita-saves");
-        } else {
-            ui::error("Failed to get HOME environment variable");
-            panic!();
+        return Ok(());
+    }
+
+    /// Applies the TOML config file, missing file is not an error
+    fn apply_file(&mut self, path: &Path, errors: &mut Vec<String>) {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return,
+            Err(err) => {
+                errors.push(format!("cannot read {}: {}", path.display(), err));
+                return;
+            }
+        };
+        let table = match content.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(err) => {
+                errors.push(format!("{}: {}", path.display(), err.message()));
+                return;
+            }
+        };
+        for (key, value) in table {
+            let value = match value {
+                toml::Value::String(s) => s,
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                _ => {
+                    errors.push(format!("{key}: unsupported value type ({})", value.type_str()));
+                    continue;
+                }
+            };
+            if let Err(err) = self.set(&key, &value) {
+                errors.push(format!("{key}: {err}"));
+            }
+        }
+    }
+
+    /// Applies `NOITA_SAVES_<KEY>` environment variables
+    fn apply_env(&mut self, errors: &mut Vec<String>) {
+        for (var, value) in env::vars() {
+            let Some(key) = var.strip_prefix("NOITA_SAVES_") else {
+                continue;
+            };
+            if key == "CONFIG" {
+                continue;
+            }
+            if let Err(err) = self.set(&key.to_lowercase(), &value) {
+                errors.push(format!("{var}: {err}"));
+            }
+        }
+    }
+}
+
+/// `NOITA_SAVES_CONFIG` or `config.toml` in the platform config directory
+pub fn config_file_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("NOITA_SAVES_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(windows) {
+        let appdata = env::var("APPDATA").ok()?;
+        return Some(PathBuf::from(appdata).join("NoitaSaves").join("config.toml"));
+    }
+    let config_home = env::var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok()?;
+    return Some(config_home.join("noita-saves").join("config.toml"));
+}
+
+static OVERRIDES: LazyLock<Overrides> = LazyLock::new(|| {
+    let mut overrides = Overrides::default();
+    let mut errors = Vec::new();
+    let path = config_file_path();
+    if let Some(path) = &path {
+        overrides.apply_file(path, &mut errors);
+    }
+    overrides.apply_env(&mut errors);
+    if !errors.is_empty() {
+        ui::fatal(&format!(
+            "Invalid configuration (config file: {}):\n{}",
+            path.map_or("<none>".to_string(), |p| p.display().to_string()),
+            errors
+                .iter()
+                .map(|err| format!("  {err}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    return overrides;
+});
+
+pub static DEBUG: LazyLock<bool> = LazyLock::new(|| OVERRIDES.debug.unwrap_or(false));
+
+/// Directory containing `Nolla_Games_Noita` (`AppData/LocalLow` on Windows)
+#[cfg(windows)]
+fn find_common_location() -> Result<PathBuf, String> {
+    if let Ok(appdata) = env::var("APPDATA") {
+        return Ok(PathBuf::from(appdata.replace("Roaming", "LocalLow")));
+    }
+    return Err("Failed to get APPDATA environment variable".to_string());
+}
+
+/// Directory containing `Nolla_Games_Noita` (`AppData/LocalLow` inside the Proton prefix elsewhere)
+#[cfg(not(windows))]
+fn find_common_location() -> Result<PathBuf, String> {
+    let (steam_roots, candidates) = steam::steam_roots();
+    if steam_roots.is_empty() {
+        return Err(format!(
+            "Cannot find Steam installation, tried:\n{}",
+            path_list(&candidates)
+        ));
+    }
+    let mut tried = Vec::new();
+    for steam_root in &steam_roots {
+        for library_folder in steam::library_folders(steam_root) {
+            let local_low = steam::proton_local_low(&library_folder);
+            if local_low.join("Nolla_Games_Noita").is_dir() {
+                return Ok(local_low);
+            }
+            tried.push(local_low.join("Nolla_Games_Noita"));
         }
     }
-    ui::debug(&format!("Common location: {}", common_location.display()));
-    return Config {
-        cache_file_name: String::from(".noita_saves_cache.json"),
-        saves_dir_path: common_location.join("Nolla_Games_Noita_Saves"),
-        current_save_path: common_location.join("Nolla_Games_Noita").join("save00"),
+    return Err(format!(
+        "Cannot find Noita save location, tried:\n{}\nLaunch Noita through Steam Proton at least once",
+        path_list(&tried)
+    ));
+}
+
+/// One indented path per line
+#[cfg(not(windows))]
+fn path_list(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("  {}", path.display()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn find_debug_location() -> Result<PathBuf, String> {
+    let home_var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    if let Ok(home) = env::var(home_var) {
+        return Ok(PathBuf::from(home).join("tmp").join("noita-saves"));
+    }
+    return Err(format!("Failed to get {home_var} environment variable"));
+}
+
+pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
+    // Not needed when both paths are overridden
+    let common_location = LazyCell::new(|| {
+        let common_location_res = if !OVERRIDES.debug_location.unwrap_or(false) {
+            find_common_location()
+        } else {
+            find_debug_location()
+        };
+        let common_location = common_location_res.unwrap_or_else(|err| ui::fatal(&err));
+        ui::debug(&format!("Common location: {}", common_location.display()));
+        return common_location;
+    });
+
+    let saves_dir_path = OVERRIDES
+        .saves_dir_path
+        .clone()
+        .unwrap_or_else(|| common_location.join("Nolla_Games_Noita_Saves"));
+    let config = Config {
+        cache_file_name: OVERRIDES
+            .cache_file_name
+            .clone()
+            .unwrap_or_else(|| String::from(".noita_saves_cache.json")),
+        autosaves_dir_path: saves_dir_path.join(".autosaves"),
+        autosaves_limit: OVERRIDES.autosaves_limit.unwrap_or(5),
+        quicksaves_limit: OVERRIDES.quicksaves_limit.unwrap_or(5),
+        watch_saves_limit: OVERRIDES.watch_saves_limit.unwrap_or(5),
+        prune_keep_last: OVERRIDES.prune_keep_last.unwrap_or(10),
+        prune_keep_days: OVERRIDES.prune_keep_days.unwrap_or(7),
+        prune_max_size: OVERRIDES.prune_max_size,
+        objects_dir_path: saves_dir_path.join(".objects"),
+        trash_dir_path: saves_dir_path.join(".trash"),
+        trash_days: OVERRIDES.trash_days.unwrap_or(30),
+        state_file_path: saves_dir_path.join(".noita_saves_state.json"),
+        storage: OVERRIDES.storage.unwrap_or(Storage::Dir),
+        check_game_running: OVERRIDES.check_game_running.unwrap_or(true),
+        #[cfg(not(windows))]
+        proc_root: OVERRIDES.proc_root.clone().unwrap_or_else(|| PathBuf::from("/proc")),
+        steam_sync_wait: OVERRIDES.steam_sync_wait.unwrap_or(30),
+        revert_watch: OVERRIDES.revert_watch.unwrap_or(60),
+        saves_dir_path,
+        current_save_path: OVERRIDES
+            .current_save_path
+            .clone()
+            .unwrap_or_else(|| common_location.join("Nolla_Games_Noita").join("save00")),
     };
+    ui::debug(&format!("{:#?}", config));
+    return config;
 });
@@ -0,0 +1,243 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use roxmltree::{Document, Node};
+
+/// Prefix of `session_stat_file` in `world_state.xml`, stands for the `stats` directory of the save
+const STATS_PREFIX: &str = "??STA/";
+
+/// Noita stores HP in units of 25
+const HP_SCALE: f64 = 25.0;
+
+/// Player state parsed from `player.xml`
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+pub struct PlayerInfo {
+    pub hp: f64,
+    pub max_hp: f64,
+    pub gold: u64,
+    pub x: f64,
+    pub y: f64,
+    pub wands: Vec<WandInfo>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+pub struct WandInfo {
+    pub name: String,
+    /// Action ids of the spells, e.g. `LIGHT_BULLET`
+    pub spells: Vec<String>,
+}
+
+/// Run state parsed from `world_state.xml` and the session stats
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RunInfo {
+    pub seed: Option<u64>,
+    /// In-game play time in seconds
+    pub play_time: f64,
+    /// Perk ids, repeated for perks picked several times
+    pub perks: Vec<String>,
+    pub orbs: usize,
+    pub kills: u64,
+    /// The player died and the run is over
+    pub ended: bool,
+    pub killed_by: Option<String>,
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+fn attr_f64(node: Node, name: &str) -> Option<f64> {
+    node.attribute(name)?.parse().ok()
+}
+
+fn has_tag(node: Node, tag: &str) -> bool {
+    node.attribute("tags")
+        .is_some_and(|tags| tags.split(',').any(|t| t == tag))
+}
+
+fn parse_wand(wand: Node) -> WandInfo {
+    let name = wand
+        .descendants()
+        .find(|n| n.has_tag_name("AbilityComponent"))
+        .and_then(|n| n.attribute("ui_name"))
+        .unwrap_or("wand");
+    WandInfo {
+        name: name.trim_start_matches('$').to_string(),
+        spells: wand
+            .descendants()
+            .filter(|n| n.has_tag_name("ItemActionComponent"))
+            .filter_map(|n| n.attribute("action_id"))
+            .map(|id| id.to_string())
+            .collect(),
+    }
+}
+
+impl PlayerInfo {
+    /// Parses `player.xml` of a save directory, `None` if the player is missing or unreadable
+    pub fn read(save_dir: &Path) -> Option<PlayerInfo> {
+        let content = fs::read_to_string(save_dir.join("player.xml")).ok()?;
+        return PlayerInfo::parse(&content);
+    }
+
+    pub fn parse(content: &str) -> Option<PlayerInfo> {
+        let document = Document::parse(content).ok()?;
+        let player = document.root_element();
+        let transform = child(player, "_Transform");
+        let damage_model = child(player, "DamageModelComponent");
+        let wallet = child(player, "WalletComponent");
+        let wands = player
+            .children()
+            .find(|n| n.has_tag_name("Entity") && n.attribute("name") == Some("inventory_quick"))
+            .map(|inventory| {
+                inventory
+                    .children()
+                    .filter(|n| n.has_tag_name("Entity") && has_tag(*n, "wand"))
+                    .map(parse_wand)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        return Some(PlayerInfo {
+            hp: damage_model.and_then(|n| attr_f64(n, "hp")).unwrap_or(0.0) * HP_SCALE,
+            max_hp: damage_model.and_then(|n| attr_f64(n, "max_hp")).unwrap_or(0.0) * HP_SCALE,
+            gold: wallet.and_then(|n| n.attribute("money")?.parse().ok()).unwrap_or(0),
+            x: transform.and_then(|n| attr_f64(n, "position.x")).unwrap_or(0.0),
+            y: transform.and_then(|n| attr_f64(n, "position.y")).unwrap_or(0.0),
+            wands,
+        });
+    }
+
+    /// Short summary for the saves list
+    pub fn summary(&self) -> String {
+        format!("♥ {:.0}/{:.0} $ {}", self.hp, self.max_hp, self.gold)
+    }
+}
+
+/// Session stats file of the current run, the latest session if `world_state.xml` does not point to one
+fn session_stats_path(save_dir: &Path, world_state: Option<Node>) -> Option<PathBuf> {
+    let pointed = world_state
+        .and_then(|n| n.attribute("session_stat_file"))
+        .and_then(|file| file.strip_prefix(STATS_PREFIX))
+        .map(|file| save_dir.join("stats").join(format!("{file}_stats.xml")))
+        .filter(|path| path.is_file());
+    if pointed.is_some() {
+        return pointed;
+    }
+    // Session files are named by their start time, so the last one by name is the latest
+    return fs::read_dir(save_dir.join("stats").join("sessions"))
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.to_string_lossy().ends_with("_stats.xml"))
+        .max();
+}
+
+impl RunInfo {
+    /// Reads the run state of a save directory, `None` if there is no `world_state.xml`
+    pub fn read(save_dir: &Path) -> Option<RunInfo> {
+        let content = fs::read_to_string(save_dir.join("world_state.xml")).ok()?;
+        let document = Document::parse(&content).ok()?;
+        let world_state = document.descendants().find(|n| n.has_tag_name("WorldStateComponent"));
+
+        let mut run = RunInfo::default();
+        if let Some(world_state) = world_state {
+            if let Some(globals) = child(world_state, "lua_globals") {
+                for global in globals.children().filter(|n| n.is_element()) {
+                    let Some(perk) = global
+                        .attribute("key")
+                        .and_then(|key| key.strip_prefix("PERK_PICKED_"))
+                        .and_then(|key| key.strip_suffix("_PICKUP_COUNT"))
+                    else {
+                        continue;
+                    };
+                    let count = global.attribute("value").and_then(|v| v.parse().ok()).unwrap_or(1);
+                    run.perks.extend(std::iter::repeat_n(perk.to_string(), count));
+                }
+            }
+            run.orbs = child(world_state, "orbs_found_thisrun")
+                .map_or(0, |orbs| orbs.children().filter(|n| n.is_element()).count());
+        }
+
+        let stats_content = session_stats_path(save_dir, world_state).and_then(|path| fs::read_to_string(path).ok());
+        if let Some(stats_document) = stats_content.as_deref().and_then(|c| Document::parse(c).ok()) {
+            let stats = stats_document.root_element();
+            run.seed = stats.attribute("world_seed").and_then(|v| v.parse().ok());
+            run.play_time = attr_f64(stats, "playtime").unwrap_or(0.0);
+            run.kills = stats
+                .attribute("enemies_killed")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+            run.ended = stats.attribute("dead") == Some("1");
+            run.killed_by = stats
+                .attribute("killed_by")
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty());
+        }
+        return Some(run);
+    }
+
+    /// Play time as `1h 05m`
+    pub fn play_time_string(&self) -> String {
+        let minutes = (self.play_time / 60.0) as u64;
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+/// Game state value that differs between two saves
+#[derive(serde::Serialize, Debug)]
+pub struct GameChange {
+    pub field: String,
+    pub from: String,
+    pub to: String,
+}
+
+fn wand_string(wand: Option<&WandInfo>) -> String {
+    wand.map_or("none".to_string(), |wand| {
+        format!("{} [{}]", wand.name, wand.spells.join(", "))
+    })
+}
+
+/// Differences in player and run state, sections missing on either side are not compared
+pub fn game_changes(
+    old: (Option<&PlayerInfo>, Option<&RunInfo>),
+    new: (Option<&PlayerInfo>, Option<&RunInfo>),
+) -> Vec<GameChange> {
+    let mut changes = Vec::new();
+    let mut compare = |field: &str, from: String, to: String| {
+        if from != to {
+            changes.push(GameChange {
+                field: field.to_string(),
+                from,
+                to,
+            });
+        }
+    };
+    if let Some((old, new)) = old.0.zip(new.0) {
+        compare("HP", format!("{:.0}", old.hp), format!("{:.0}", new.hp));
+        compare("Max HP", format!("{:.0}", old.max_hp), format!("{:.0}", new.max_hp));
+        compare("Gold", old.gold.to_string(), new.gold.to_string());
+        compare(
+            "Position",
+            format!("x {:.0}, y {:.0}", old.x, old.y),
+            format!("x {:.0}, y {:.0}", new.x, new.y),
+        );
+        for i in 0..old.wands.len().max(new.wands.len()) {
+            compare(
+                &format!("Wand {}", i + 1),
+                wand_string(old.wands.get(i)),
+                wand_string(new.wands.get(i)),
+            );
+        }
+    }
+    if let Some((old, new)) = old.1.zip(new.1) {
+        let seed = |run: &RunInfo| run.seed.map_or("unknown".to_string(), |seed| seed.to_string());
+        compare("Seed", seed(old), seed(new));
+        compare("Play time", old.play_time_string(), new.play_time_string());
+        compare("Orbs", old.orbs.to_string(), new.orbs.to_string());
+        compare("Kills", old.kills.to_string(), new.kills.to_string());
+        compare("Perks", old.perks.join(", "), new.perks.join(", "));
+        compare("Run ended", old.ended.to_string(), new.ended.to_string());
+    }
+    return changes;
+}
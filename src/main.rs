@@ -1,25 +1,74 @@
+mod archive;
 mod commands;
 mod config;
+mod game;
+mod processes;
+mod prune;
+mod state;
+mod steam;
+mod store;
 mod ui;
 mod utils;
 
-use console::style;
+use std::{env, process};
 
-use crate::{
-    commands::{CMD_MAP, CMD_SHORTCUTS},
-    utils::SaveInfo,
-};
+use crate::utils::SaveInfo;
+
+/// Runs a single command given as process arguments, e.g. `noita-saves load 3`
+fn run_once(args: &[String], assume_yes: bool) -> ! {
+    ui::set_interactive(false);
+    ui::set_assume_yes(assume_yes);
+    purge_trash();
+    let cmd_name_or_alias = args.first().map_or("list".to_string(), |s| s.to_lowercase());
+    let arg = Some(args.iter().skip(1).cloned().collect::<Vec<_>>().join(" ")).filter(|s| !s.trim().is_empty());
+    let saves_mb = SaveInfo::all();
+    let result = commands::run(saves_mb.as_ref(), &cmd_name_or_alias, arg.as_deref().map(str::trim));
+    ui::json_finish(&cmd_name_or_alias, result.map_or_else(|f| f.status(), |_| "ok"));
+    process::exit(result.map_or_else(|f| f as i32, |_| 0));
+}
+
+/// Deletes expired saves from the trash, failures are only reported
+fn purge_trash() {
+    if let Err(err) = utils::purge_trash() {
+        ui::error(&format!("Failed to empty old saves from the trash: {}", err));
+    }
+}
 
 fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // Options go before the command, anything after it is the argument, e.g. a save named `--yes`
+    let options_count = args.iter().take_while(|a| *a == "--json" || *a == "--yes").count();
+    let options = args.drain(..options_count).collect::<Vec<_>>();
+    let json_output = options.iter().any(|a| a == "--json");
+    let assume_yes = options.iter().any(|a| a == "--yes");
+    if json_output {
+        // Also a way to get the saves list with just `--json`
+        ui::set_json_output();
+        run_once(&args, assume_yes);
+    }
+    if !args.is_empty() {
+        run_once(&args, assume_yes);
+    }
+    // Confirmations of the interactive mode are never skipped
+    if assume_yes {
+        ui::error("--yes needs a command to confirm, e.g. `noita-saves --yes load 3`");
+        process::exit(commands::Failure::Validation as i32);
+    }
+
     ui::welcome();
+    purge_trash();
     loop {
+        // Offer to reapply a load reverted by Steam Cloud after `play`, errors are already reported to the user
+        commands::interactive_reapply_reverted_load().ok();
+
         // Tell user we are already working at their request )
-        ui::lnlnwrite("\nSaves:");
+        ui::lnlnwrite(&format!("\n{}", commands::saves_header()));
         ui::lnwrite("Loading...").update_later();
 
         // Get current progress and available saves
         let current_save_mb = SaveInfo::current();
         let saves_mb = SaveInfo::all();
+        let autosaves = SaveInfo::autosaves().unwrap_or_default();
 
         // Available actions for prompt
         let mut actions = if current_save_mb.is_some() {
@@ -27,26 +76,16 @@ fn main() {
         } else {
             vec!["load", "delete", "play", "quit"]
         };
-
-        // Print available saves
-        if let Some(saves) = &saves_mb {
-            if !saves.is_empty() {
-                let i_width = saves.len().to_string().len();
-                for (i, save) in saves.iter().enumerate() {
-                    ui::lnwrite(&format!(
-                        "{:i_width$} ❯ {}",
-                        i + 1,
-                        save.to_string(current_save_mb.as_ref())
-                    ));
-                }
-            } else {
-                ui::lnlnwrite(&style("< Nothing >").dim().to_string());
-            }
-        } else {
-            ui::error("Cannot load saves");
+        if !autosaves.is_empty() {
+            actions.insert(actions.len() - 2, "undo");
+        }
+        if saves_mb.is_none() {
             actions = vec!["play", "quit"];
         }
 
+        // Print available saves
+        commands::print_saves(saves_mb.as_ref(), current_save_mb.as_ref(), &autosaves);
+
         // Ask user for action
         let response_mb = ui::ask(&ui::main_prompt(&actions));
         let response;
@@ -63,14 +102,8 @@ fn main() {
             (response.to_lowercase(), None)
         };
 
-        // Call command
-        if let Some(cmd) = CMD_MAP.get(cmd_name_or_alias.as_str()) {
-            cmd(saves_mb.as_ref(), arg);
-        } else if let Some(cmd_name) = CMD_SHORTCUTS.get(cmd_name_or_alias.as_str()) {
-            CMD_MAP.get(cmd_name).unwrap()(saves_mb.as_ref(), arg);
-        } else {
-            commands::cmd_not_found(cmd_name_or_alias.as_str());
-        }
+        // Call command, errors are already reported to the user
+        commands::run(saves_mb.as_ref(), &cmd_name_or_alias, arg).ok();
 
         // Exit if user wants to quit
         if ["q", "quit"].contains(&cmd_name_or_alias.as_str()) {
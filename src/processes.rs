@@ -0,0 +1,216 @@
+use std::io;
+#[cfg(not(windows))]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[cfg(not(windows))]
+use crate::config::CONFIG;
+
+/// Executable names of the game, also seen under Wine/Proton
+const NOITA_EXECUTABLES: [&str; 2] = ["noita.exe", "noita_dev.exe"];
+
+/// Source of running process names, a fake process table can be used instead of the system one
+pub trait ProcessLister {
+    fn process_names(&self) -> io::Result<Vec<String>>;
+}
+
+/// Reads a `/proc`-like directory, Wine/Proton processes show their Windows executable there
+#[cfg(not(windows))]
+pub struct ProcLister {
+    pub root: PathBuf,
+}
+
+/// Last component of a Unix or Windows path
+#[cfg(not(windows))]
+fn executable_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[cfg(not(windows))]
+impl ProcessLister for ProcLister {
+    fn process_names(&self) -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        for entry in self.root.read_dir()? {
+            let path = entry?.path();
+            let is_pid = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().chars().all(|c| c.is_ascii_digit()));
+            if !is_pid {
+                continue;
+            }
+            // Processes may exit while being listed
+            if let Ok(comm) = fs::read_to_string(path.join("comm")) {
+                names.push(comm.trim().to_string());
+            }
+            if let Some(arg0) = read_arg0(&path) {
+                names.push(executable_name(&arg0).to_string());
+            }
+        }
+        return Ok(names);
+    }
+}
+
+#[cfg(not(windows))]
+fn read_arg0(process_dir: &Path) -> Option<String> {
+    let cmdline = fs::read(process_dir.join("cmdline")).ok()?;
+    let arg0 = cmdline.split(|&b| b == 0).next()?;
+    return Some(String::from_utf8_lossy(arg0).into_owned()).filter(|s| !s.is_empty());
+}
+
+/// Lists processes with `tasklist`
+#[cfg(windows)]
+pub struct TasklistLister;
+
+#[cfg(windows)]
+impl ProcessLister for TasklistLister {
+    fn process_names(&self) -> io::Result<Vec<String>> {
+        let output = std::process::Command::new("tasklist")
+            .args(["/FO", "CSV", "/NH"])
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!("tasklist exited with {}", output.status)));
+        }
+        // Lines look like `"noita.exe","1234","Console","1","1 234 K"`
+        return Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split(',').next())
+            .map(|name| name.trim_matches('"').to_string())
+            .collect());
+    }
+}
+
+#[cfg(windows)]
+pub fn system_lister() -> Box<dyn ProcessLister> {
+    Box::new(TasklistLister)
+}
+
+#[cfg(not(windows))]
+pub fn system_lister() -> Box<dyn ProcessLister> {
+    Box::new(ProcLister {
+        root: CONFIG.proc_root.clone(),
+    })
+}
+
+pub fn is_noita_running_with(lister: &dyn ProcessLister) -> io::Result<bool> {
+    return Ok(lister
+        .process_names()?
+        .iter()
+        .any(|name| NOITA_EXECUTABLES.iter().any(|exe| name.eq_ignore_ascii_case(exe))));
+}
+
+/// Whether the game is running, an error means it is unknown
+pub fn is_noita_running() -> io::Result<bool> {
+    return is_noita_running_with(system_lister().as_ref());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeLister(io::Result<Vec<&'static str>>);
+
+    impl ProcessLister for FakeLister {
+        fn process_names(&self) -> io::Result<Vec<String>> {
+            match &self.0 {
+                Ok(names) => Ok(names.iter().map(|name| name.to_string()).collect()),
+                Err(err) => Err(io::Error::new(err.kind(), err.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn fake_lister_running() {
+        let lister = FakeLister(Ok(vec!["steam", "Noita.exe"]));
+        assert!(is_noita_running_with(&lister).unwrap());
+    }
+
+    #[test]
+    fn fake_lister_not_running() {
+        let lister = FakeLister(Ok(vec!["steam", "noita-saves", "wineserver"]));
+        assert!(!is_noita_running_with(&lister).unwrap());
+    }
+
+    #[test]
+    fn fake_lister_error() {
+        let lister = FakeLister(Err(io::Error::from(io::ErrorKind::PermissionDenied)));
+        assert!(is_noita_running_with(&lister).is_err());
+    }
+
+    /// Fake `/proc` with `(pid, comm, cmdline)` entries, removed on drop
+    #[cfg(not(windows))]
+    struct FakeProc(PathBuf);
+
+    #[cfg(not(windows))]
+    impl FakeProc {
+        fn new(name: &str, processes: &[(&str, &str, &[u8])]) -> FakeProc {
+            let root = std::env::temp_dir().join(format!("noita-saves-{}-{name}", std::process::id()));
+            fs::create_dir_all(root.join("self")).unwrap();
+            for (pid, comm, cmdline) in processes {
+                let dir = root.join(pid);
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("comm"), format!("{comm}\n")).unwrap();
+                fs::write(dir.join("cmdline"), cmdline).unwrap();
+            }
+            return FakeProc(root);
+        }
+
+        fn lister(&self) -> ProcLister {
+            ProcLister { root: self.0.clone() }
+        }
+    }
+
+    #[cfg(not(windows))]
+    impl Drop for FakeProc {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.0).ok();
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn proc_lister_reads_comm_and_arg0() {
+        let proc = FakeProc::new(
+            "names",
+            &[("1", "systemd", b"/sbin/init\0splash\0"), ("42", "bash", b"")],
+        );
+        let mut names = proc.lister().process_names().unwrap();
+        names.sort();
+        assert_eq!(names, ["bash", "init", "systemd"]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn proc_lister_finds_proton_noita() {
+        // `comm` may name the Wine loader, the Windows path of the game is in `cmdline`
+        let proc = FakeProc::new(
+            "proton",
+            &[
+                ("100", "steam", b"/home/me/.steam/steam/ubuntu12_32/steam\0"),
+                (
+                    "200",
+                    "wine64-preload",
+                    b"C:\\Program Files (x86)\\Steam\\steamapps\\common\\Noita\\noita.exe\0",
+                ),
+            ],
+        );
+        assert!(is_noita_running_with(&proc.lister()).unwrap());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn proc_lister_without_noita() {
+        let proc = FakeProc::new("idle", &[("100", "steam", b"/usr/bin/steam\0")]);
+        assert!(!is_noita_running_with(&proc.lister()).unwrap());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn proc_lister_missing_root() {
+        let lister = ProcLister {
+            root: std::env::temp_dir().join("noita-saves-no-such-proc"),
+        };
+        assert!(is_noita_running_with(&lister).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+
+use crate::{config::CONFIG, utils::SaveInfo};
+
+/// Retention rules of `prune`
+pub struct Rules {
+    /// Newest saves always kept
+    pub keep_last: usize,
+    /// Days back for which the newest save of each day is kept
+    pub keep_days: usize,
+    /// Total size of kept saves, the oldest ones beyond it are deleted
+    pub max_size: Option<u64>,
+}
+
+impl Rules {
+    pub fn from_config() -> Rules {
+        Rules {
+            keep_last: CONFIG.prune_keep_last,
+            keep_days: CONFIG.prune_keep_days,
+            max_size: CONFIG.prune_max_size,
+        }
+    }
+}
+
+/// Space a save takes on disk, archives count with their packed size
+fn disk_size(save: &SaveInfo) -> u64 {
+    save.stat.packed_size.unwrap_or(save.stat.size)
+}
+
+/// Saves the retention rules would delete, oldest first
+///
+/// Kept are the newest `keep_last` saves and the newest save of each of the last `keep_days` days,
+/// then the oldest kept saves go until the rest fits into `max_size`.
+/// Pinned saves are never deleted, but their size counts
+pub fn plan<'a>(saves: &'a [SaveInfo], rules: &Rules) -> Vec<&'a SaveInfo> {
+    return plan_on(saves, rules, Local::now().date_naive());
+}
+
+fn plan_on<'a>(saves: &'a [SaveInfo], rules: &Rules, today: NaiveDate) -> Vec<&'a SaveInfo> {
+    let mut newest_first: Vec<&SaveInfo> = saves.iter().collect();
+    newest_first.sort_by_key(|save| std::cmp::Reverse(save.ctime));
+
+    let mut kept: HashSet<&str> = newest_first
+        .iter()
+        .enumerate()
+        .filter(|(i, save)| *i < rules.keep_last || save.meta.pinned)
+        .map(|(_, save)| save.name.as_str())
+        .collect();
+    // Too many days to count is the same as every day
+    let keep_days = i64::try_from(rules.keep_days).ok().and_then(Duration::try_days);
+    let mut days_seen = HashSet::new();
+    for save in &newest_first {
+        let day = DateTime::<Local>::from(save.ctime).date_naive();
+        if keep_days.is_none_or(|keep_days| today - day < keep_days) && days_seen.insert(day) {
+            kept.insert(&save.name);
+        }
+    }
+
+    if let Some(max_size) = rules.max_size {
+        let mut total = 0;
+        for save in &newest_first {
+            if !kept.contains(save.name.as_str()) {
+                continue;
+            }
+            total += disk_size(save);
+            if total > max_size && !save.meta.pinned {
+                kept.remove(save.name.as_str());
+            }
+        }
+    }
+
+    let mut pruned: Vec<&SaveInfo> = saves.iter().filter(|save| !kept.contains(save.name.as_str())).collect();
+    pruned.sort_by_key(|save| save.ctime);
+    return pruned;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{SaveMeta, SaveStat};
+    use chrono::TimeZone;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 18).unwrap()
+    }
+
+    /// Save made `days_ago` days before `today` at `hour` o'clock
+    fn save(name: &str, days_ago: u64, hour: u32, size: u64) -> SaveInfo {
+        let day = today() - chrono::Days::new(days_ago);
+        let ctime = Local
+            .from_local_datetime(&day.and_hms_opt(hour, 0, 0).unwrap())
+            .unwrap();
+        let stat = SaveStat {
+            size,
+            count: 1,
+            ..SaveStat::default()
+        };
+        return SaveInfo::for_test(name, ctime.into(), stat, SaveMeta::default());
+    }
+
+    fn pinned(mut save: SaveInfo) -> SaveInfo {
+        save.meta.pinned = true;
+        return save;
+    }
+
+    fn names(pruned: Vec<&SaveInfo>) -> Vec<&str> {
+        pruned.into_iter().map(|save| save.name.as_str()).collect()
+    }
+
+    fn rules(keep_last: usize, keep_days: usize, max_size: Option<u64>) -> Rules {
+        Rules {
+            keep_last,
+            keep_days,
+            max_size,
+        }
+    }
+
+    #[test]
+    fn keep_last_only() {
+        let saves = [save("a", 3, 12, 1), save("b", 2, 12, 1), save("c", 1, 12, 1)];
+        assert_eq!(names(plan_on(&saves, &rules(2, 0, None), today())), ["a"]);
+    }
+
+    #[test]
+    fn newest_of_each_day() {
+        let saves = [
+            save("old", 10, 12, 1),
+            save("morning", 1, 9, 1),
+            save("evening", 1, 21, 1),
+            save("today", 0, 12, 1),
+        ];
+        // Today and yesterday are within 2 days, the 10 days old save is not
+        assert_eq!(names(plan_on(&saves, &rules(0, 2, None), today())), ["old", "morning"]);
+    }
+
+    #[test]
+    fn keep_last_and_days_add_up() {
+        let saves = [
+            save("old", 10, 12, 1),
+            save("morning", 1, 9, 1),
+            save("evening", 1, 21, 1),
+        ];
+        assert_eq!(names(plan_on(&saves, &rules(1, 2, None), today())), ["old", "morning"]);
+    }
+
+    #[test]
+    fn huge_keep_days_keeps_every_day() {
+        let saves = [save("ancient", 20000, 12, 1), save("new", 0, 12, 1)];
+        let pruned = plan_on(&saves, &rules(0, usize::MAX, None), today());
+        assert!(pruned.is_empty());
+        let pruned = plan_on(&saves, &rules(0, 999_999_999_999_999, None), today());
+        assert!(pruned.is_empty());
+    }
+
+    #[test]
+    fn budget_drops_oldest_kept() {
+        let saves = [save("a", 3, 12, 40), save("b", 2, 12, 40), save("c", 1, 12, 40)];
+        assert_eq!(names(plan_on(&saves, &rules(3, 0, Some(100)), today())), ["a"]);
+    }
+
+    #[test]
+    fn budget_counts_packed_size() {
+        let mut archive = save("archive", 2, 12, 1000);
+        archive.stat.packed_size = Some(10);
+        let saves = [archive, save("dir", 1, 12, 50)];
+        assert!(plan_on(&saves, &rules(2, 0, Some(60)), today()).is_empty());
+    }
+
+    #[test]
+    fn pinned_survive_budget_but_count() {
+        let saves = [
+            pinned(save("pinned", 5, 12, 80)),
+            save("a", 2, 12, 30),
+            save("b", 1, 12, 30),
+        ];
+        // Newest first: b (30), a (60), then the pinned save goes over the budget but stays
+        assert_eq!(
+            names(plan_on(&saves, &rules(3, 0, Some(100)), today())),
+            Vec::<&str>::new()
+        );
+        // Newest first: b (30), the pinned save (110) stays, a (140) goes
+        let saves = [
+            save("a", 6, 12, 30),
+            pinned(save("pinned", 5, 12, 80)),
+            save("b", 1, 12, 30),
+        ];
+        assert_eq!(names(plan_on(&saves, &rules(3, 0, Some(100)), today())), ["a"]);
+    }
+
+    #[test]
+    fn pinned_kept_beyond_keep_last() {
+        let saves = [
+            pinned(save("pinned", 9, 12, 1)),
+            save("a", 2, 12, 1),
+            save("b", 1, 12, 1),
+        ];
+        assert_eq!(names(plan_on(&saves, &rules(1, 0, None), today())), ["a"]);
+    }
+}
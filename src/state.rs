@@ -0,0 +1,27 @@
+use std::{fs, io, time::SystemTime};
+
+use crate::config::CONFIG;
+
+/// What happened to the current progress, kept between runs in the saves directory
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
+pub struct State {
+    /// Name of the save the current progress was loaded from
+    #[serde(default)]
+    pub loaded: Option<String>,
+    /// When the save was loaded (or the progress was saved into it)
+    #[serde(default)]
+    pub loaded_at: Option<SystemTime>,
+}
+
+impl State {
+    pub fn read() -> State {
+        fs::read_to_string(&CONFIG.state_file_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write(&self) -> io::Result<()> {
+        fs::write(&CONFIG.state_file_path, serde_json::to_string(self)?)
+    }
+}
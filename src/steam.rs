@@ -0,0 +1,162 @@
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use regex::Regex;
+
+pub const NOITA_APP_ID: &str = "881100";
+
+/// Existing Steam installation directories and all the candidate paths checked for them
+pub fn steam_roots() -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let mut candidates = Vec::new();
+    if cfg!(windows) {
+        for var in ["ProgramFiles(x86)", "ProgramFiles"] {
+            if let Ok(program_files) = env::var(var) {
+                candidates.push(PathBuf::from(program_files).join("Steam"));
+            }
+        }
+    } else if let Ok(home) = env::var("HOME") {
+        let home = PathBuf::from(home);
+        candidates.extend([
+            home.join(".steam").join("steam"),
+            home.join(".steam").join("root"),
+            home.join(".local").join("share").join("Steam"),
+            // Flatpak and Snap installations
+            home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+            home.join("snap/steam/common/.local/share/Steam"),
+        ]);
+    }
+
+    // `~/.steam/steam` is usually a symlink to one of the others
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for candidate in &candidates {
+        if let Ok(root) = candidate.canonicalize()
+            && !roots.contains(&root)
+        {
+            roots.push(root);
+        }
+    }
+    return (roots, candidates);
+}
+
+/// All library folders of a Steam installation, including the installation itself
+#[cfg(not(windows))]
+pub fn library_folders(steam_root: &Path) -> Vec<PathBuf> {
+    let mut folders = vec![steam_root.to_path_buf()];
+    let vdf_path = steam_root.join("steamapps").join("libraryfolders.vdf");
+    if let Ok(content) = fs::read_to_string(vdf_path) {
+        let path_re = Regex::new(r#""path"\s+"((?:[^"\\]|\\.)*)""#).unwrap();
+        for captures in path_re.captures_iter(&content) {
+            let folder = PathBuf::from(captures[1].replace(r"\\", r"\"));
+            let folder = folder.canonicalize().unwrap_or(folder);
+            if !folders.contains(&folder) {
+                folders.push(folder);
+            }
+        }
+    }
+    return folders;
+}
+
+/// `LocalLow` directory inside the Proton prefix of Noita in the given library
+#[cfg(not(windows))]
+pub fn proton_local_low(library_folder: &Path) -> PathBuf {
+    library_folder
+        .join("steamapps")
+        .join("compatdata")
+        .join(NOITA_APP_ID)
+        .join("pfx/drive_c/users/steamuser/AppData/LocalLow")
+}
+
+/// Steam updates `remotecache.vdf` while syncing, so a recent change means the sync is running
+const SYNC_ACTIVITY: Duration = Duration::from_secs(5);
+
+/// Steam Cloud state of the Noita files
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CloudSync {
+    /// Every file is synchronized
+    Idle,
+    /// Number of files waiting for upload or download
+    Pending(usize),
+    /// Steam has just updated its cache
+    Syncing,
+}
+
+impl fmt::Display for CloudSync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloudSync::Idle => write!(f, "idle"),
+            CloudSync::Pending(1) => write!(f, "1 file pending"),
+            CloudSync::Pending(count) => write!(f, "{count} files pending"),
+            CloudSync::Syncing => write!(f, "syncing"),
+        }
+    }
+}
+
+/// Whether Steam Cloud is turned off for Noita in the game properties of the user owning `remote_cache_path`
+fn cloud_disabled(remote_cache_path: &Path) -> bool {
+    // `userdata/<user>/881100/remotecache.vdf` -> `userdata/<user>/7/remote/sharedconfig.vdf`
+    let Some(user_dir) = remote_cache_path.parent().and_then(Path::parent) else {
+        return false;
+    };
+    let Ok(content) = fs::read_to_string(user_dir.join("7").join("remote").join("sharedconfig.vdf")) else {
+        return false;
+    };
+    let app_re = Regex::new(&format!(r#"(?i)"{NOITA_APP_ID}"\s*\{{[^{{}}]*"cloudenabled"\s+"0""#)).unwrap();
+    return app_re.is_match(&content);
+}
+
+/// `remotecache.vdf` of Noita for every Steam user who has Steam Cloud on for it
+fn remote_cache_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let (steam_roots, _) = steam_roots();
+    for steam_root in steam_roots {
+        let Ok(users) = steam_root.join("userdata").read_dir() else {
+            continue;
+        };
+        for user in users.filter_map(|entry| entry.ok()) {
+            let path = user.path().join(NOITA_APP_ID).join("remotecache.vdf");
+            if path.is_file() && !cloud_disabled(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    return paths;
+}
+
+/// Number of files not in sync, `syncstate` 1 means synchronized
+fn pending_files(content: &str) -> usize {
+    let file_re = Regex::new(r#""[^"]*"\s*\{([^{}]*)\}"#).unwrap();
+    let state_re = Regex::new(r#""syncstate"\s+"(\d+)""#).unwrap();
+    file_re
+        .captures_iter(content)
+        .filter_map(|file| state_re.captures(&file[1]).map(|state| state[1].to_string()))
+        .filter(|state| state != "1")
+        .count()
+}
+
+/// State of Steam Cloud for Noita, `None` if Steam Cloud is not used
+pub fn cloud_sync_state() -> Option<CloudSync> {
+    let paths = remote_cache_paths();
+    if paths.is_empty() {
+        return None;
+    }
+    let mut pending = 0;
+    for path in &paths {
+        let recently_modified = fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < SYNC_ACTIVITY);
+        if recently_modified {
+            return Some(CloudSync::Syncing);
+        }
+        pending += fs::read_to_string(path).map_or(0, |content| pending_files(&content));
+    }
+    return Some(if pending > 0 {
+        CloudSync::Pending(pending)
+    } else {
+        CloudSync::Idle
+    });
+}
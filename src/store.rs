@@ -0,0 +1,179 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::{
+    config::CONFIG,
+    ui::ProgressBar,
+    utils::{self, SaveStat},
+};
+
+pub const MANIFEST_EXTENSION: &str = ".manifest.json";
+
+/// Save stored in the object store: every file is kept once under its content hash
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default)]
+pub struct Manifest {
+    /// Relative paths of directories, `/`-separated
+    pub dirs: Vec<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct ManifestFile {
+    /// Relative path, `/`-separated
+    pub path: String,
+    pub hash: String,
+    pub size: u64,
+}
+
+impl Manifest {
+    pub fn read(path: &Path) -> io::Result<Manifest> {
+        let content = fs::read_to_string(path)?;
+        return Ok(serde_json::from_str(&content)?);
+    }
+}
+
+pub fn is_manifest(path: &Path) -> bool {
+    path.is_file() && path.to_string_lossy().ends_with(MANIFEST_EXTENSION)
+}
+
+pub fn hash_reader(reader: &mut impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    return Ok(format!("{:x}", hasher.finalize()));
+}
+
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    return hash_reader(&mut BufReader::new(File::open(path)?));
+}
+
+fn object_path(hash: &str) -> PathBuf {
+    CONFIG.objects_dir_path.join(&hash[..2]).join(hash)
+}
+
+pub fn relative_path_string(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn join_relative_path(base: &Path, relative_path: &str) -> PathBuf {
+    relative_path
+        .split('/')
+        .fold(base.to_path_buf(), |path, part| path.join(part))
+}
+
+/// Stores files of `src` directory as objects and writes `dst` manifest
+pub fn store_dir_with_progress(src: &Path, dst: &Path, progress_bar_title: Option<&str>) -> io::Result<SaveStat> {
+    let stat = SaveStat::scan(src);
+    let mut bar = ProgressBar::new(stat.count, progress_bar_title, 60);
+    let mut manifest = Manifest::default();
+    let mut progress = 0;
+    for entry in WalkDir::new(src).min_depth(1) {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy() == CONFIG.cache_file_name {
+            continue;
+        }
+        let relative_path = relative_path_string(entry.path().strip_prefix(src).unwrap());
+        if entry.file_type().is_dir() {
+            manifest.dirs.push(relative_path);
+            continue;
+        }
+
+        let hash = hash_file(entry.path())?;
+        let object_path = object_path(&hash);
+        if !object_path.exists() {
+            // Copy under a temporary name, so an interrupted copy is never taken for a stored object
+            fs::create_dir_all(object_path.parent().unwrap())?;
+            let part_path = utils::sibling_path(&object_path, ".part");
+            fs::copy(entry.path(), &part_path)?;
+            fs::rename(&part_path, &object_path)?;
+        }
+        manifest.files.push(ManifestFile {
+            path: relative_path,
+            hash,
+            size: entry.metadata()?.len(),
+        });
+        progress += 1;
+        bar.update(progress);
+    }
+
+    let part_path = utils::sibling_path(dst, ".part");
+    fs::write(&part_path, serde_json::to_string(&manifest)?)?;
+    fs::rename(&part_path, dst)?;
+    return Ok(stat);
+}
+
+/// Recreates a new `dst` directory from `src` manifest
+pub fn restore_with_progress(src: &Path, dst: &Path, progress_bar_title: Option<&str>) -> io::Result<()> {
+    let manifest = Manifest::read(src)?;
+    let mut bar = ProgressBar::new(manifest.files.len(), progress_bar_title, 60);
+    fs::create_dir(dst)?;
+    for dir in &manifest.dirs {
+        fs::create_dir_all(join_relative_path(dst, dir))?;
+    }
+    for (i, file) in manifest.files.iter().enumerate() {
+        fs::copy(object_path(&file.hash), join_relative_path(dst, &file.path))?;
+        bar.update(i + 1);
+    }
+    return Ok(());
+}
+
+pub fn scan(src: &Path) -> io::Result<SaveStat> {
+    let manifest = Manifest::read(src)?;
+    return Ok(SaveStat {
+        size: manifest.files.iter().map(|f| f.size).sum(),
+        count: manifest.files.len(),
+        ..SaveStat::default()
+    });
+}
+
+/// Manifests whose objects must be kept
+fn all_manifests() -> io::Result<Vec<PathBuf>> {
+    let mut manifests = Vec::new();
+    // Saves in the trash can still be restored
+    for dir in [&CONFIG.saves_dir_path, &CONFIG.trash_dir_path] {
+        if !dir.exists() {
+            continue;
+        }
+        for entry in dir.read_dir()? {
+            let path = entry?.path();
+            if is_manifest(&path) {
+                manifests.push(path);
+            }
+        }
+    }
+    return Ok(manifests);
+}
+
+/// Removes objects no manifest refers to, returns count and size of removed objects
+pub fn collect_garbage() -> io::Result<(usize, u64)> {
+    if !CONFIG.objects_dir_path.exists() {
+        return Ok((0, 0));
+    }
+    let mut referenced = HashSet::new();
+    for manifest_path in all_manifests()? {
+        // An unreadable manifest may still refer to anything, so keep everything
+        let manifest = Manifest::read(&manifest_path)?;
+        referenced.extend(manifest.files.into_iter().map(|f| f.hash));
+    }
+
+    let mut removed = (0, 0);
+    for entry in WalkDir::new(&CONFIG.objects_dir_path).min_depth(2) {
+        let entry = entry?;
+        if !entry.file_type().is_file() || referenced.contains(entry.file_name().to_string_lossy().as_ref()) {
+            continue;
+        }
+        removed.1 += entry.metadata()?.len();
+        removed.0 += 1;
+        fs::remove_file(entry.path())?;
+    }
+    return Ok(removed);
+}
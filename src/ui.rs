@@ -1,9 +1,14 @@
 use chrono::Duration;
 use console::{Color, Term, style};
 use regex::Regex;
+use serde_json::{Map, Value, json};
 use std::{
     io::{self, Write},
-    sync::{LazyLock, Mutex},
+    process,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
     time::Instant,
 };
 
@@ -11,32 +16,121 @@ use crate::config::DEBUG;
 
 struct Memo {
     lines_to_update: Option<usize>,
+    /// Output that is to be updated, repeating it is skipped where it cannot be redrawn
+    output_to_update: Option<String>,
 }
 
 pub struct PostHandler {
     lines_printed: usize,
+    output: String,
 }
 
 impl PostHandler {
     fn from_output(output: &str) -> Self {
         PostHandler {
             lines_printed: output.split('\n').count(),
+            output: output.to_string(),
         }
     }
 
     pub fn update_later(&self) {
-        MEMO.lock().unwrap().lines_to_update = Some(self.lines_printed);
+        let mut memo = MEMO.lock().unwrap();
+        memo.lines_to_update = Some(self.lines_printed);
+        memo.output_to_update = Some(self.output.clone());
     }
 }
 
-static TERM: LazyLock<Term> = LazyLock::new(|| Term::buffered_stdout());
-static MEMO: LazyLock<Mutex<Memo>> = LazyLock::new(|| Mutex::new(Memo { lines_to_update: None }));
+static TERM: LazyLock<Term> = LazyLock::new(Term::buffered_stdout);
+static MEMO: LazyLock<Mutex<Memo>> = LazyLock::new(|| {
+    Mutex::new(Memo {
+        lines_to_update: None,
+        output_to_update: None,
+    })
+});
+static INTERACTIVE: AtomicBool = AtomicBool::new(true);
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Non-interactive mode is used when running a single command from the command line
+pub fn set_interactive(interactive: bool) {
+    INTERACTIVE.store(interactive, Ordering::Relaxed);
+}
+
+pub fn is_interactive() -> bool {
+    INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Whether printed lines can be cleared and drawn again, not when a single command's output is piped or captured
+fn can_redraw() -> bool {
+    is_interactive() && TERM.is_term()
+}
+
+/// Answers yes to every confirmation, set by `--yes`
+pub fn set_assume_yes(assume_yes: bool) {
+    ASSUME_YES.store(assume_yes, Ordering::Relaxed);
+}
+
+/// Result of a command in JSON output mode, printed once by `json_finish`
+struct JsonOutput {
+    errors: Vec<String>,
+    fields: Map<String, Value>,
+}
+
+/// `Some` in JSON output mode, all human-readable output is suppressed then
+static JSON_OUTPUT: Mutex<Option<JsonOutput>> = Mutex::new(None);
+
+pub fn set_json_output() {
+    *JSON_OUTPUT.lock().unwrap() = Some(JsonOutput {
+        errors: Vec::new(),
+        fields: Map::new(),
+    });
+}
+
+pub fn is_json_output() -> bool {
+    JSON_OUTPUT.lock().unwrap().is_some()
+}
+
+/// Adds a field to the JSON result, does nothing in normal mode
+pub fn json_field(key: &str, value: Value) {
+    if let Some(output) = JSON_OUTPUT.lock().unwrap().as_mut() {
+        output.fields.insert(key.to_string(), value);
+    }
+}
+
+/// Prints the JSON result: `{"command", "ok", "status", "errors", ...fields}`
+pub fn json_finish(command: &str, status: &str) {
+    if let Some(output) = JSON_OUTPUT.lock().unwrap().take() {
+        let mut result = Map::new();
+        result.insert("command".to_string(), json!(command));
+        result.insert("ok".to_string(), json!(status == "ok"));
+        result.insert("status".to_string(), json!(status));
+        result.insert("errors".to_string(), json!(output.errors));
+        result.extend(output.fields);
+        println!("{}", Value::Object(result));
+    }
+}
+
+/// Reports an error that makes running impossible and exits
+pub fn fatal(msg: &str) -> ! {
+    error(msg);
+    json_finish("", "config");
+    process::exit(1);
+}
 
 pub fn lnwrite(msg: &str) -> PostHandler {
+    if is_json_output() {
+        return PostHandler::from_output("");
+    }
+
     let mut memo = MEMO.lock().expect("Cannot access MEMO");
     if let Some(l2u) = memo.lines_to_update {
-        TERM.clear_last_lines(l2u).ok();
+        if can_redraw() {
+            TERM.clear_last_lines(l2u).ok();
+        } else if memo.output_to_update.as_deref() == Some(msg) {
+            // The same status again, e.g. while waiting, is printed once
+            return PostHandler::from_output(msg);
+        }
         memo.lines_to_update = None;
+        memo.output_to_update = None;
     }
 
     TERM.write_line(msg).ok();
@@ -58,13 +152,18 @@ pub fn lnlnwrite_highlighted(border_color: Color, msg: &str) -> PostHandler {
 }
 
 pub fn error(msg: &str) {
+    if let Some(output) = JSON_OUTPUT.lock().unwrap().as_mut() {
+        output.errors.push(console::strip_ansi_codes(msg).to_string());
+        return;
+    }
+
     let mut buf = style("Error:\n").red().to_string();
     buf.push_str(msg);
     lnlnwrite_highlighted(Color::Red, &buf);
 }
 
 pub fn debug(msg: &str) {
-    if DEBUG {
+    if *DEBUG {
         let mut buf = style("Debug:\n").cyan().to_string();
         buf.push_str(msg);
         lnlnwrite_highlighted(Color::Cyan, &buf);
@@ -89,12 +188,22 @@ pub fn main_prompt(actions: &[&str]) -> String {
 }
 
 pub fn ask(prompt: &str) -> Option<String> {
+    if !INTERACTIVE.load(Ordering::Relaxed) {
+        error(&format!("Missing argument: {}", console::strip_ansi_codes(prompt)));
+        return None;
+    }
+
     let mut memo = MEMO.lock().expect("Cannot access MEMO");
-    if let Some(l2u) = memo.lines_to_update {
+    if let Some(l2u) = memo.lines_to_update
+        && can_redraw()
+    {
         TERM.clear_last_lines(l2u).ok();
         TERM.flush().ok();
-        memo.lines_to_update = None;
     }
+    memo.lines_to_update = None;
+    memo.output_to_update = None;
+    // Background threads may report something while waiting for the answer
+    drop(memo);
 
     let mut buf = String::new();
     print!("\n{}{}", prompt, style(" ❯ ").cyan());
@@ -104,6 +213,22 @@ pub fn ask(prompt: &str) -> Option<String> {
     return Some(response).filter(|s| !s.is_empty());
 }
 
+/// Asks a yes/no question, anything but `y` is no; `None` in non-interactive mode without `--yes`
+pub fn confirm(prompt: &str) -> Option<bool> {
+    if ASSUME_YES.load(Ordering::Relaxed) {
+        return Some(true);
+    }
+    if !INTERACTIVE.load(Ordering::Relaxed) {
+        error(&format!(
+            "Confirmation required: {} (pass --yes to confirm)",
+            console::strip_ansi_codes(prompt)
+        ));
+        return None;
+    }
+    let response = ask(&format!("{prompt} {}", style("[y/N]").dim()));
+    return Some(response.is_some_and(|r| r.eq_ignore_ascii_case("y") || r.eq_ignore_ascii_case("yes")));
+}
+
 pub struct ProgressBar {
     target: usize,
     title: Option<String>,
@@ -142,6 +267,10 @@ impl ProgressBar {
     }
 
     fn draw(&mut self) {
+        // Each step would be a new line where the bar cannot be redrawn
+        if !can_redraw() {
+            return;
+        }
         self.redrawn += 1;
         let filled = self.visible_status;
         let empty = self.bar_width - filled;
@@ -176,22 +305,19 @@ impl ProgressBar {
 pub fn welcome() {
     let gh_link = style("https://github.com/kiria-f/noita-saves").cyan();
 
-    lnlnwrite_highlighted(
-        Color::Green,
-        &dim_squares(
-            [
-                &style("Welcome to NoitaSaves!").bold().green().to_string(),
-                "",
-                &format!("{}", style("To make a save, you should first quit the game").bold()),
-                &format!("{}", style("You also need to close Noita before loading a save").bold()),
-                "Turn off Steam sync in the game settings (if it's enabled)",
-                "  Otherwise, do not load a save during Steam sync, it may corrupt the current game state",
-                "  If the selected save has not loaded, just load it one more time",
-                "  (It may happen due to steam sync)",
-                "You can also manage NoitaSaves shortcuts with [x] command",
-                &format!("(Check GitHub repo for more info: {gh_link})"),
-            ]
-            .join("\n"),
-        ),
-    );
+    let mut lines = vec![
+        style("Welcome to NoitaSaves!").bold().green().to_string(),
+        "".to_string(),
+        format!("{}", style("To make a save, you should first quit the game").bold()),
+        format!("{}", style("You also need to close Noita before loading a save").bold()),
+        "Turn off Steam sync in the game settings (if it's enabled)".to_string(),
+        "  Otherwise, do not load a save during Steam sync, it may corrupt the current game state".to_string(),
+        "  NoitaSaves checks every load and warns if Steam sync brings back the old progress".to_string(),
+    ];
+    if cfg!(windows) {
+        lines.push("You can also manage NoitaSaves shortcuts with [x] command".to_string());
+    }
+    lines.push(format!("(Check GitHub repo for more info: {gh_link})"));
+
+    lnlnwrite_highlighted(Color::Green, &dim_squares(lines.join("\n")));
 }
@@ -1,29 +1,58 @@
 use std::{
-    collections::HashSet,
-    fs, io,
+    cell::OnceCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt, fs, io,
     path::{Path, PathBuf},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
-use crate::{config::CONFIG, ui::ProgressBar};
+use crate::{
+    archive,
+    config::CONFIG,
+    game::{PlayerInfo, RunInfo},
+    store,
+    ui::ProgressBar,
+};
 use bytesize::ByteSize;
 use chrono::{DateTime, Local};
 use console::style;
+use serde_json::{Map, Value, json};
 use walkdir::WalkDir;
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
+#[derive(serde::Deserialize, serde::Serialize, Debug, Default, PartialEq)]
 pub struct SaveStat {
     pub size: u64,
     pub count: usize,
+    /// Size of the archive file for archived saves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packed_size: Option<u64>,
+    /// Hash over relative paths and hashes of all files, see `fingerprint`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
 }
 
 impl SaveStat {
+    /// Cache lives inside a save directory or next to a save archive
+    pub fn cache_path(save_path: &Path) -> PathBuf {
+        if save_path.is_dir() {
+            save_path.join(&CONFIG.cache_file_name)
+        } else {
+            sibling_path(save_path, &CONFIG.cache_file_name)
+        }
+    }
+
     pub fn read_cache(save_path: &Path) -> io::Result<SaveStat> {
-        let content = fs::read_to_string(save_path.join(&CONFIG.cache_file_name))?;
+        let content = fs::read_to_string(SaveStat::cache_path(save_path))?;
         return Ok(serde_json::from_str::<SaveStat>(&content)?);
     }
 
     pub fn scan(save_path: &Path) -> SaveStat {
+        if archive::is_archive(save_path) {
+            return archive::scan(save_path).unwrap_or_default();
+        }
+        if store::is_manifest(save_path) {
+            return store::scan(save_path).unwrap_or_default();
+        }
         SaveStat {
             size: WalkDir::new(save_path)
                 .into_iter()
@@ -39,6 +68,8 @@ impl SaveStat {
                 .filter(|e| e.file_type().is_file())
                 .filter(|e| e.file_name().to_string_lossy() != CONFIG.cache_file_name)
                 .count(),
+            packed_size: None,
+            fingerprint: None,
         }
     }
 
@@ -55,9 +86,136 @@ impl SaveStat {
     }
 
     pub fn write_cache(&self, save_path: &Path) -> io::Result<()> {
-        let content = serde_json::to_string(self)?;
-        fs::write(save_path.join(&CONFIG.cache_file_name), content)?;
-        Ok(())
+        update_cache(save_path, self)
+    }
+}
+
+/// Relative `/`-separated paths and sizes of the files of any kind of save
+pub fn save_files(save_path: &Path) -> io::Result<Vec<(String, u64)>> {
+    if archive::is_archive(save_path) {
+        return archive::files(save_path);
+    }
+    if store::is_manifest(save_path) {
+        let manifest = store::Manifest::read(save_path)?;
+        return Ok(manifest.files.into_iter().map(|f| (f.path, f.size)).collect());
+    }
+    let mut files = Vec::new();
+    for entry in WalkDir::new(save_path).min_depth(1) {
+        let entry = entry?;
+        if entry.file_type().is_file() && entry.file_name().to_string_lossy() != CONFIG.cache_file_name {
+            let relative_path = entry.path().strip_prefix(save_path).unwrap();
+            files.push((store::relative_path_string(relative_path), entry.metadata()?.len()));
+        }
+    }
+    return Ok(files);
+}
+
+/// File of a save as compared by `diff`
+#[derive(Debug, PartialEq)]
+pub struct IndexedFile {
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Files of any kind of save by relative `/`-separated path, hashes every file
+pub fn file_index(save_path: &Path) -> io::Result<BTreeMap<String, IndexedFile>> {
+    if archive::is_archive(save_path) {
+        return Ok(archive::hashed_files(save_path)?
+            .into_iter()
+            .map(|(path, size, hash)| (path, IndexedFile { size, hash }))
+            .collect());
+    }
+    if store::is_manifest(save_path) {
+        let manifest = store::Manifest::read(save_path)?;
+        return Ok(manifest
+            .files
+            .into_iter()
+            .map(|f| {
+                (
+                    f.path,
+                    IndexedFile {
+                        size: f.size,
+                        hash: f.hash,
+                    },
+                )
+            })
+            .collect());
+    }
+    let mut index = BTreeMap::new();
+    for (path, size) in save_files(save_path)? {
+        let hash = store::hash_file(&store::join_relative_path(save_path, &path))?;
+        index.insert(path, IndexedFile { size, hash });
+    }
+    return Ok(index);
+}
+
+/// Content fingerprint of any kind of save, equal fingerprints mean identical files
+pub fn fingerprint(save_path: &Path) -> io::Result<String> {
+    return index_fingerprint(&file_index(save_path)?);
+}
+
+/// Fingerprint of an already built `file_index`
+pub fn index_fingerprint(index: &BTreeMap<String, IndexedFile>) -> io::Result<String> {
+    let mut listing = String::new();
+    for (path, file) in index {
+        listing.push_str(&format!("{path}\0{}\n", file.hash));
+    }
+    return store::hash_reader(&mut listing.as_bytes());
+}
+
+/// Writes fields of `value` into the save cache, keeping the other fields
+fn update_cache(save_path: &Path, value: &impl serde::Serialize) -> io::Result<()> {
+    let cache_path = SaveStat::cache_path(save_path);
+    let mut cache = fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Map<String, Value>>(&content).ok())
+        .unwrap_or_default();
+    if let Value::Object(fields) = serde_json::to_value(value)? {
+        cache.extend(fields);
+    }
+    fs::write(cache_path, serde_json::to_string(&cache)?)?;
+    Ok(())
+}
+
+/// Per-save information kept in the cache next to `SaveStat`
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
+pub struct SaveMeta {
+    /// Creation time of the save, preserved through renames
+    #[serde(default)]
+    pub ctime: Option<SystemTime>,
+    /// Name of the save this one was copied from
+    #[serde(default)]
+    pub parent: Option<String>,
+    /// Player state at the moment of saving
+    #[serde(default)]
+    pub player: Option<PlayerInfo>,
+    /// Run state at the moment of saving
+    #[serde(default)]
+    pub run: Option<RunInfo>,
+    /// Made by quick-save, such saves are rotated like autosaves
+    #[serde(default)]
+    pub quick: bool,
+    /// Made by `watch` when the game exited, rotated like quick-saves
+    #[serde(default)]
+    pub exit: bool,
+    /// Protected from deletion until unpinned
+    #[serde(default)]
+    pub pinned: bool,
+    /// When the save was moved to the trash
+    #[serde(default)]
+    pub deleted: Option<SystemTime>,
+}
+
+impl SaveMeta {
+    pub fn read(save_path: &Path) -> SaveMeta {
+        fs::read_to_string(SaveStat::cache_path(save_path))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write(&self, save_path: &Path) -> io::Result<()> {
+        update_cache(save_path, self)
     }
 }
 
@@ -66,9 +224,28 @@ pub struct SaveInfo {
     pub name: String,
     pub ctime: SystemTime,
     pub stat: SaveStat,
+    pub meta: SaveMeta,
+    /// Nesting level of copies in the listing, 0 for saves without a listed parent
+    pub depth: usize,
+    /// Content fingerprint, computed on first use
+    fingerprint: OnceCell<Option<String>>,
 }
 
 impl SaveInfo {
+    /// Save that exists only in memory
+    #[cfg(test)]
+    pub fn for_test(name: &str, ctime: SystemTime, stat: SaveStat, meta: SaveMeta) -> SaveInfo {
+        SaveInfo {
+            path: PathBuf::from(name),
+            name: name.to_string(),
+            ctime,
+            stat,
+            meta,
+            depth: 0,
+            fingerprint: OnceCell::new(),
+        }
+    }
+
     pub fn current() -> Option<Self> {
         let meta = CONFIG.current_save_path.metadata().ok()?;
         return Some(SaveInfo {
@@ -76,58 +253,226 @@ impl SaveInfo {
             name: "".to_string(),
             ctime: meta.created().or_else(|_| meta.modified()).ok()?,
             stat: SaveStat::scan(&CONFIG.current_save_path),
+            meta: SaveMeta::default(),
+            depth: 0,
+            fingerprint: OnceCell::new(),
         });
     }
 
-    pub fn new(name: String) -> Option<Self> {
-        let path = CONFIG.saves_dir_path.join(&name);
-        if !path.exists() {
+    pub fn new(path: PathBuf) -> Option<Self> {
+        let file_name = path.file_name()?.to_string_lossy().into_owned();
+        let name = if path.is_dir() {
+            file_name
+        } else if archive::is_archive(&path) {
+            file_name.strip_suffix(archive::ARCHIVE_EXTENSION)?.to_string()
+        } else if store::is_manifest(&path) {
+            file_name.strip_suffix(store::MANIFEST_EXTENSION)?.to_string()
+        } else {
             return None;
-        }
+        };
         let metadata = path.metadata().ok()?;
-        let stat = SaveStat::read_cache_or_scan(&CONFIG.saves_dir_path.join(&name));
+        let stat = SaveStat::read_cache_or_scan(&path);
+        let meta = SaveMeta::read(&path);
+        let ctime = match meta.ctime {
+            Some(ctime) => ctime,
+            None => metadata.created().or_else(|_| metadata.modified()).ok()?,
+        };
         return Some(SaveInfo {
             path,
             name,
-            ctime: metadata.created().or_else(|_| metadata.modified()).ok()?,
+            ctime,
             stat,
+            meta,
+            depth: 0,
+            fingerprint: OnceCell::new(),
         });
     }
 
-    pub fn all() -> Option<Vec<SaveInfo>> {
-        let mut saves: Vec<SaveInfo> = CONFIG
-            .saves_dir_path
+    fn all_in(dir: &Path) -> Option<Vec<SaveInfo>> {
+        let mut saves: Vec<SaveInfo> = dir
             .read_dir()
             .ok()?
-            .filter_map(|entry| SaveInfo::new(entry.ok()?.file_name().to_string_lossy().into_owned()))
+            .filter_map(|entry| entry.ok())
+            // Hidden entries are service directories (autosaves etc.), not saves
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(|entry| SaveInfo::new(entry.path()))
             .collect();
         saves.sort_by_key(|save| save.ctime);
         return Some(saves);
     }
 
+    /// Saves sorted by creation time, with copies placed right after their parents
+    pub fn all() -> Option<Vec<SaveInfo>> {
+        let saves = SaveInfo::all_in(&CONFIG.saves_dir_path)?;
+        let names: HashSet<String> = saves.iter().map(|s| s.name.clone()).collect();
+        let mut roots = Vec::new();
+        let mut children: HashMap<String, Vec<SaveInfo>> = HashMap::new();
+        for save in saves {
+            match save.meta.parent.clone().filter(|parent| names.contains(parent)) {
+                Some(parent) => children.entry(parent).or_default().push(save),
+                None => roots.push(save),
+            }
+        }
+
+        fn visit(
+            mut save: SaveInfo,
+            depth: usize,
+            children: &mut HashMap<String, Vec<SaveInfo>>,
+            out: &mut Vec<SaveInfo>,
+        ) {
+            save.depth = depth;
+            let own_children = children.remove(&save.name).unwrap_or_default();
+            out.push(save);
+            for child in own_children {
+                visit(child, depth + 1, children, out);
+            }
+        }
+        let mut ordered = Vec::new();
+        for root in roots {
+            visit(root, 0, &mut children, &mut ordered);
+        }
+        // Parent cycles can only come from hand-edited caches, still list those saves
+        ordered.extend(children.into_values().flatten());
+        return Some(ordered);
+    }
+
+    /// Snapshots of the progress taken automatically before loads, oldest first
+    pub fn autosaves() -> Option<Vec<SaveInfo>> {
+        if !CONFIG.autosaves_dir_path.exists() {
+            return Some(Vec::new());
+        }
+        SaveInfo::all_in(&CONFIG.autosaves_dir_path)
+    }
+
+    /// Fingerprint from the cache, hashes the contents and caches the result if it is not there yet
+    pub fn fingerprint(&self) -> Option<&str> {
+        self.fingerprint
+            .get_or_init(|| {
+                if let Some(fingerprint) = &self.stat.fingerprint {
+                    return Some(fingerprint.clone());
+                }
+                let fingerprint = fingerprint(&self.path).ok()?;
+                // The current progress changes all the time, its fingerprint is not cached
+                if self.path != CONFIG.current_save_path {
+                    update_cache(&self.path, &json!({"fingerprint": fingerprint})).ok();
+                }
+                return Some(fingerprint);
+            })
+            .as_deref()
+    }
+
+    /// Saves in the trash, the most recently deleted last
+    pub fn trashed() -> Option<Vec<SaveInfo>> {
+        if !CONFIG.trash_dir_path.exists() {
+            return Some(Vec::new());
+        }
+        let mut saves = SaveInfo::all_in(&CONFIG.trash_dir_path)?;
+        saves.sort_by_key(|save| save.meta.deleted);
+        return Some(saves);
+    }
+
+    /// Contents are identical, only hashed when size and count match
     pub fn is_current(&self, current_save: Option<&SaveInfo>) -> bool {
-        matches!(&current_save, Some(current_save) if current_save.stat == self.stat)
+        let Some(current_save) = current_save else {
+            return false;
+        };
+        if current_save.stat.size != self.stat.size || current_save.stat.count != self.stat.count {
+            return false;
+        }
+        return self.fingerprint().is_some() && self.fingerprint() == current_save.fingerprint();
+    }
+
+    /// Structured record for JSON output, `index` is the position in the listing (1-based)
+    pub fn to_json(&self, index: Option<usize>, current_save: Option<&SaveInfo>) -> serde_json::Value {
+        json!({
+            "index": index,
+            "name": self.name,
+            "path": self.path.to_string_lossy(),
+            "ctime": DateTime::<Local>::from(self.ctime).to_rfc3339(),
+            "size": self.stat.size,
+            "packed_size": self.stat.packed_size,
+            "count": self.stat.count,
+            "is_current": self.is_current(current_save),
+            "parent": self.meta.parent,
+            "player": self.meta.player,
+            "run": self.meta.run,
+            "pinned": self.meta.pinned,
+        })
+    }
+
+    /// Last modification time of the save contents
+    pub fn mtime(&self) -> Option<SystemTime> {
+        if !self.path.is_dir() {
+            return self.path.metadata().and_then(|m| m.modified()).ok();
+        }
+        return WalkDir::new(&self.path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.file_name().to_string_lossy() != CONFIG.cache_file_name)
+            .filter_map(|e| e.metadata().ok()?.modified().ok())
+            .max();
+    }
+
+    /// Whether the cached stats differ from the actual contents, `None` if there is no cache
+    pub fn is_cache_stale(&self) -> Option<bool> {
+        let cached = SaveStat::read_cache(&self.path).ok()?;
+        let actual = SaveStat::scan(&self.path);
+        return Some(
+            cached.size != actual.size || cached.count != actual.count || cached.packed_size != actual.packed_size,
+        );
+    }
+
+    /// World seed of the run, saves with the same seed belong to the same run
+    pub fn run_seed(&self) -> Option<u64> {
+        self.meta.run.as_ref().and_then(|run| run.seed)
+    }
+
+    /// The player was dead at the moment of saving
+    pub fn run_ended(&self) -> bool {
+        self.meta.run.as_ref().is_some_and(|run| run.ended)
     }
 
     pub fn to_string(&self, current_save: Option<&SaveInfo>) -> String {
-        let additional_info = format!(
-            "[{} | {}]",
+        let size = match self.stat.packed_size {
+            Some(packed_size) => format!("{} ❯ {}", ByteSize::b(self.stat.size), ByteSize::b(packed_size)),
+            None => ByteSize::b(self.stat.size).to_string(),
+        };
+        let mut additional_info = format!(
+            "[{} | {}",
             DateTime::<Local>::from(self.ctime).format("%b %-d %H:%M:%S"),
-            ByteSize::b(self.stat.size)
+            size
         );
+        if let Some(player) = &self.meta.player {
+            additional_info.push_str(&format!(" | {}", player.summary()));
+        }
+        if let Some(seed) = self.run_seed() {
+            additional_info.push_str(&format!(" | seed {seed}"));
+        }
+        additional_info.push(']');
 
+        let name = if self.depth > 0 {
+            format!("{}{} {}", "  ".repeat(self.depth - 1), style("└").dim(), &self.name)
+        } else {
+            self.name.clone()
+        };
+        let name = if self.run_ended() {
+            format!("{}  {}", name, style("<Run ended>").red().dim())
+        } else {
+            name
+        };
+        let name = if self.meta.pinned {
+            format!("{}  {}", name, style("<Pinned>").cyan().dim())
+        } else {
+            name
+        };
         if self.is_current(current_save) {
-            return style(format!(
-                "{}  {}  {}",
-                &self.name,
-                &additional_info,
-                style("<Current>").dim()
-            ))
-            .green()
-            .bold()
-            .to_string();
+            return style(format!("{}  {}  {}", &name, &additional_info, style("<Current>").dim()))
+                .green()
+                .bold()
+                .to_string();
         } else {
-            return format!("{}  {}", &self.name, style(&additional_info).dim());
+            return format!("{}  {}", &name, style(&additional_info).dim());
         }
     }
 }
@@ -162,21 +507,337 @@ pub fn copy_dir_with_progress(
     return Ok(());
 }
 
+/// Path of the same kind of save (directory, archive or manifest) with another name in `dir`
+fn save_path_in(save: &SaveInfo, dir: &Path, name: &str) -> PathBuf {
+    let file_name = save.path.file_name().unwrap_or_default().to_string_lossy();
+    let suffix = file_name.strip_prefix(save.name.as_str()).unwrap_or_default();
+    return dir.join(format!("{name}{suffix}"));
+}
+
+fn save_path_with_name(save: &SaveInfo, name: &str) -> PathBuf {
+    save_path_in(save, save.path.parent().unwrap_or(Path::new("")), name)
+}
+
+/// Moves a save of any kind along with its cache, `meta` is written first, returns the new path
+fn move_save(save: &SaveInfo, new_path: PathBuf, meta: &SaveMeta) -> io::Result<PathBuf> {
+    if new_path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", new_path.display()),
+        ));
+    }
+    meta.write(&save.path)?;
+
+    let cache_path = SaveStat::cache_path(&save.path);
+    let is_file = save.path.is_file();
+    fs::rename(&save.path, &new_path)?;
+    if is_file
+        && cache_path.exists()
+        && let Err(err) = fs::rename(&cache_path, SaveStat::cache_path(&new_path))
+    {
+        fs::rename(&new_path, &save.path).ok();
+        return Err(err);
+    }
+    return Ok(new_path);
+}
+
+/// Renames a save of any kind along with its cache, returns the new path
+pub fn rename_save(save: &SaveInfo, new_name: &str) -> io::Result<PathBuf> {
+    // Keep the place in the listing, the file system may update creation time on rename
+    let mut meta = SaveMeta::read(&save.path);
+    meta.ctime = Some(save.ctime);
+    return move_save(save, save_path_with_name(save, new_name), &meta);
+}
+
+/// First of `base`, `base (2)`, `base (3)`... for which `exists` is false
+pub fn unique_name(base: &str, exists: impl Fn(&str) -> bool) -> String {
+    if !exists(base) {
+        return base.to_string();
+    }
+    return (2..)
+        .map(|n| format!("{base} ({n})"))
+        .find(|name| !exists(name))
+        .unwrap();
+}
+
+/// First of `name`, `name (2)`, `name (3)`... not taken in `dir`
+fn free_name_in(save: &SaveInfo, dir: &Path, name: &str) -> String {
+    return unique_name(name, |name| save_path_in(save, dir, name).exists());
+}
+
+/// Moves a save into the trash, recording the deletion time
+pub fn trash_save(save: &SaveInfo) -> io::Result<PathBuf> {
+    fs::create_dir_all(&CONFIG.trash_dir_path)?;
+    let mut meta = SaveMeta::read(&save.path);
+    meta.ctime = Some(save.ctime);
+    meta.deleted = Some(SystemTime::now());
+    let name = free_name_in(save, &CONFIG.trash_dir_path, &save.name);
+    return move_save(save, save_path_in(save, &CONFIG.trash_dir_path, &name), &meta);
+}
+
+/// Moves a save from the trash back to the saves, under `name (2)` etc. if the name is taken
+pub fn restore_save(save: &SaveInfo) -> io::Result<PathBuf> {
+    let mut meta = SaveMeta::read(&save.path);
+    meta.deleted = None;
+    let name = free_name_in(save, &CONFIG.saves_dir_path, &save.name);
+    return move_save(save, save_path_in(save, &CONFIG.saves_dir_path, &name), &meta);
+}
+
+/// Permanently deletes trashed saves older than `trash_days`, returns their names
+pub fn purge_trash() -> io::Result<Vec<String>> {
+    // Zero days keeps the trash until it is emptied by hand
+    if CONFIG.trash_days == 0 {
+        return Ok(Vec::new());
+    }
+    // So many days that nothing can be that old yet
+    let Some(secs) = (CONFIG.trash_days as u64).checked_mul(24 * 60 * 60) else {
+        return Ok(Vec::new());
+    };
+    let max_age = Duration::from_secs(secs);
+    let trashed = SaveInfo::trashed().ok_or_else(|| io::Error::other("Cannot read trash"))?;
+    let expired: Vec<&SaveInfo> = trashed
+        .iter()
+        .filter(|save| {
+            save.meta
+                .deleted
+                .and_then(|deleted| deleted.elapsed().ok())
+                .is_some_and(|age| age > max_age)
+        })
+        .collect();
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+    delete_dirs_with_progress(&expired.iter().map(|s| s.path.as_path()).collect(), None)?;
+    store::collect_garbage()?;
+    return Ok(expired.iter().map(|s| s.name.clone()).collect());
+}
+
+/// Copies a save of any kind under a new name and records the original as its parent
+pub fn copy_save(save: &SaveInfo, new_name: &str, progress_bar_title: Option<&str>) -> io::Result<PathBuf> {
+    let new_path = save_path_with_name(save, new_name);
+    if new_path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", new_path.display()),
+        ));
+    }
+
+    if save.path.is_dir() {
+        copy_dir_with_progress(&save.path, &new_path, true, true, progress_bar_title)?;
+    } else {
+        // Archives are copied as is, manifests share the stored objects
+        let mut bar = ProgressBar::new(1, progress_bar_title, 60);
+        fs::copy(&save.path, &new_path)?;
+        save.stat.write_cache(&new_path)?;
+        bar.update(1);
+    }
+    let meta = SaveMeta {
+        ctime: None,
+        parent: Some(save.name.clone()),
+        quick: false,
+        exit: false,
+        pinned: false,
+        ..save.meta.clone()
+    };
+    meta.write(&new_path)?;
+    return Ok(new_path);
+}
+
+/// Error of a multi-step file operation, remembers which step has failed
+#[derive(Debug)]
+pub struct StepError {
+    pub step: &'static str,
+    pub source: io::Error,
+    /// Whether the destination was left as it was before the operation
+    pub untouched: bool,
+}
+
+impl StepError {
+    fn new(step: &'static str, source: io::Error) -> Self {
+        StepError {
+            step,
+            source,
+            untouched: true,
+        }
+    }
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.step, self.source)
+    }
+}
+
+/// `save00` -> `save00<suffix>` in the same parent directory
+pub fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(suffix);
+    return path.with_file_name(file_name);
+}
+
+/// Cleans up after an interrupted `replace_dir_with_progress`: puts the backup of `dst` back
+/// if `dst` is missing and removes the staging directory
+pub fn recover_interrupted_replace(dst: &Path) -> Result<(), StepError> {
+    let staging = sibling_path(dst, ".noita_saves_staging");
+    let backup = sibling_path(dst, ".noita_saves_backup");
+    if backup.exists() {
+        if dst.exists() {
+            fs::remove_dir_all(&backup).map_err(|e| StepError::new("Removing stale backup", e))?;
+        } else {
+            fs::rename(&backup, dst).map_err(|e| StepError::new("Restoring backup of interrupted load", e))?;
+        }
+    }
+    if staging.exists() {
+        fs::remove_dir_all(&staging).map_err(|e| StepError::new("Removing stale staging directory", e))?;
+    }
+    return Ok(());
+}
+
+/// Replaces `dst` with a copy of `src` (directory, archive or manifest) without ever leaving `dst` half-copied.
+///
+/// `src` is copied (or unpacked) into a staging directory next to `dst` and verified first,
+/// then `dst` is moved aside and the staging directory takes its place.
+/// If any step fails, `dst` is restored untouched.
+pub fn replace_dir_with_progress(src: &Path, dst: &Path, progress_bar_title: Option<&str>) -> Result<(), StepError> {
+    let staging = sibling_path(dst, ".noita_saves_staging");
+    let backup = sibling_path(dst, ".noita_saves_backup");
+
+    recover_interrupted_replace(dst)?;
+
+    // Copy and verify
+    let discard_staging = |step, err| {
+        fs::remove_dir_all(&staging).ok();
+        return StepError::new(step, err);
+    };
+    let expected = if archive::is_archive(src) {
+        let expected = SaveStat::read_cache_or_scan(src);
+        archive::unpack_with_progress(src, &staging, expected.count, progress_bar_title)
+            .map_err(|e| discard_staging("Unpacking save into staging directory", e))?;
+        expected
+    } else if store::is_manifest(src) {
+        store::restore_with_progress(src, &staging, progress_bar_title)
+            .map_err(|e| discard_staging("Restoring save from object store", e))?;
+        SaveStat::read_cache_or_scan(src)
+    } else {
+        copy_dir_with_progress(src, &staging, true, false, progress_bar_title)
+            .map_err(|e| discard_staging("Copying save into staging directory", e))?;
+        SaveStat::scan(src)
+    };
+    let copied = SaveStat::scan(&staging);
+    if copied.size != expected.size || copied.count != expected.count {
+        return Err(discard_staging(
+            "Verifying copied save",
+            io::Error::other(format!(
+                "expected {} files ({}), got {} files ({})",
+                expected.count,
+                ByteSize::b(expected.size),
+                copied.count,
+                ByteSize::b(copied.size)
+            )),
+        ));
+    }
+
+    // Swap
+    let had_dst = dst.exists();
+    if had_dst {
+        fs::rename(dst, &backup).map_err(|e| discard_staging("Moving current progress aside", e))?;
+    }
+    if let Err(err) = fs::rename(&staging, dst) {
+        if had_dst && let Err(restore_err) = fs::rename(&backup, dst) {
+            return Err(StepError {
+                step: "Restoring current progress",
+                source: io::Error::other(format!(
+                    "{restore_err} (after: {err}), your progress is kept in {}",
+                    backup.display()
+                )),
+                untouched: false,
+            });
+        }
+        return Err(discard_staging("Moving loaded save into place", err));
+    }
+    if had_dst {
+        // Not critical, the leftover is removed on the next load
+        fs::remove_dir_all(&backup).ok();
+    }
+    return Ok(());
+}
+
+/// Copies current progress into the autosaves directory, returns the autosave path
+pub fn autosave_current(progress_bar_title: Option<&str>) -> io::Result<PathBuf> {
+    fs::create_dir_all(&CONFIG.autosaves_dir_path)?;
+    let base_name = Local::now().format("%Y-%m-%d %H-%M-%S").to_string();
+    let name = unique_name(&base_name, |name| CONFIG.autosaves_dir_path.join(name).exists());
+    let path = CONFIG.autosaves_dir_path.join(name);
+    copy_dir_with_progress(&CONFIG.current_save_path, &path, false, true, progress_bar_title)?;
+    return Ok(path);
+}
+
+/// Removes the oldest autosaves exceeding `CONFIG.autosaves_limit`
+pub fn rotate_autosaves() -> io::Result<()> {
+    let autosaves = SaveInfo::autosaves().ok_or_else(|| io::Error::other("Cannot read autosaves"))?;
+    let excess = autosaves.len().saturating_sub(CONFIG.autosaves_limit);
+    for autosave in &autosaves[..excess] {
+        fs::remove_dir_all(&autosave.path)?;
+    }
+    return Ok(());
+}
+
+/// Deletes the oldest quick-saves beyond the limit, pinned ones are neither deleted nor counted,
+/// returns names of the deleted ones
+pub fn rotate_quicksaves() -> io::Result<Vec<String>> {
+    let saves = SaveInfo::all_in(&CONFIG.saves_dir_path).ok_or_else(|| io::Error::other("Cannot read saves"))?;
+    let quicksaves: Vec<&SaveInfo> = saves
+        .iter()
+        .filter(|save| save.meta.quick && !save.meta.pinned)
+        .collect();
+    let excess = &quicksaves[..quicksaves.len().saturating_sub(CONFIG.quicksaves_limit)];
+    if excess.is_empty() {
+        return Ok(Vec::new());
+    }
+    delete_dirs_with_progress(&excess.iter().map(|s| s.path.as_path()).collect(), None)?;
+    return Ok(excess.iter().map(|s| s.name.clone()).collect());
+}
+
+/// Moves the oldest saves of `watch` beyond the limit to the trash, pinned ones are neither moved nor counted,
+/// returns names of the moved ones
+pub fn rotate_watch_saves() -> io::Result<Vec<String>> {
+    let saves = SaveInfo::all_in(&CONFIG.saves_dir_path).ok_or_else(|| io::Error::other("Cannot read saves"))?;
+    let watch_saves: Vec<&SaveInfo> = saves
+        .iter()
+        .filter(|save| save.meta.exit && !save.meta.pinned)
+        .collect();
+    let excess = &watch_saves[..watch_saves.len().saturating_sub(CONFIG.watch_saves_limit)];
+    let mut trashed = Vec::new();
+    for save in excess {
+        trash_save(save)?;
+        trashed.push(save.name.clone());
+    }
+    return Ok(trashed);
+}
+
+/// Deletes save directories, archives and manifests (with their caches)
 pub fn delete_dirs_with_progress(dirs: &HashSet<&Path>, progress_bar_title: Option<&str>) -> Result<(), io::Error> {
-    let total_files_count = dirs
-        .into_iter()
-        .map(|p| SaveStat::read_cache_or_scan(p.as_ref()).count)
-        .sum();
+    let total_files_count = dirs.iter().map(|p| SaveStat::read_cache_or_scan(p).count).sum();
     let mut bar = ProgressBar::new(total_files_count, progress_bar_title, 60);
     let mut progress = 0;
     for dir in dirs {
+        if dir.is_file() {
+            progress += SaveStat::read_cache_or_scan(dir).count;
+            fs::remove_file(dir)?;
+            let cache_path = SaveStat::cache_path(dir);
+            if cache_path.exists() {
+                fs::remove_file(cache_path)?;
+            }
+            bar.update(progress);
+            continue;
+        }
         for entry in WalkDir::new(dir).contents_first(true) {
             let entry = entry?;
             let path = entry.path();
             if entry.file_type().is_dir() {
-                fs::remove_dir(&path)?;
+                fs::remove_dir(path)?;
             } else {
-                fs::remove_file(&path)?;
+                fs::remove_file(path)?;
             }
             if entry.file_name().to_string_lossy() != CONFIG.cache_file_name {
                 progress += 1;